@@ -0,0 +1,61 @@
+use crate::vector::Vector;
+
+/// Point attracting the boids around it, or repelling them if its strength is negative
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Attractor {
+    pub position: Vector,
+    /// velocity change per step of a boid on the attractor, negative for a repulsor
+    pub strength: f32,
+    /// the pull decreases linearly down to 0 at this distance
+    pub radius: f32,
+}
+
+impl Attractor {
+    pub fn new(position: Vector, strength: f32, radius: f32) -> Self {
+        Attractor {
+            position,
+            strength,
+            radius,
+        }
+    }
+
+    /// Adds to `steering` the pull on a boid at `position`. `center` is the position
+    /// of the attractor, or of its image closest to the boid. Returns false if the boid is out of reach
+    pub fn pull(&self, position: &Vector, center: &Vector, steering: &mut Vector) -> bool {
+        let mut direction = *center;
+        direction.subtract(position);
+        let distance = direction.hypot();
+        if distance > self.radius || distance <= 1e-6 {
+            return false;
+        }
+        steering.add_scaled(&direction, self.strength * (1.0 - distance / self.radius) / distance);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pull_decreases_with_the_distance() {
+        let center = Vector { x: 1.0, y: 0.0 };
+        let attractor = Attractor::new(center, 2.0, 4.0);
+
+        let mut near = Vector::new();
+        assert!(attractor.pull(&Vector { x: 0.0, y: 0.0 }, &center, &mut near));
+        assert!((near.x - 1.5).abs() < 1e-6);
+        assert_eq!(near.y, 0.0);
+
+        let mut far = Vector::new();
+        assert!(attractor.pull(&Vector { x: -2.0, y: 0.0 }, &center, &mut far));
+        assert!(far.x > 0.0 && far.x < near.x);
+
+        assert!(!attractor.pull(&Vector { x: -4.0, y: 0.0 }, &center, &mut far));
+
+        let repulsor = Attractor::new(center, -2.0, 4.0);
+        let mut push = Vector::new();
+        assert!(repulsor.pull(&Vector { x: 0.0, y: 0.0 }, &center, &mut push));
+        assert!((push.x + 1.5).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,145 @@
+//! Runs a world for a number of steps without any window, writing its
+//! metrics, snapshots, trajectory or boids to files at a fixed interval.
+
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::process::exit;
+use std::time::{Duration, Instant};
+
+use boids_core::cli::{usage, HeadlessOptions, Options};
+use boids_core::export::CsvExporter;
+use boids_core::metrics::{FlockMetrics, CSV_HEADER};
+use boids_core::scenario::Scenario;
+use boids_core::snapshot;
+use boids_core::trajectory::TrajectoryWriter;
+use boids_core::world::World;
+
+/// output files of the run
+struct Sinks {
+    metrics: Option<BufWriter<File>>,
+    snapshots: Option<std::path::PathBuf>,
+    trajectory: Option<TrajectoryWriter<BufWriter<File>>>,
+    csv: Option<CsvExporter<BufWriter<File>>>,
+}
+
+impl Sinks {
+    fn create(options: &HeadlessOptions, world: &World) -> Result<Self, String> {
+        let metrics = match &options.metrics {
+            Some(path) => {
+                let file = File::create(path).map_err(|e| format!("Cannot create metrics '{}' : {}", path.display(), e))?;
+                let mut output = BufWriter::new(file);
+                writeln!(output, "step,time,{}", CSV_HEADER).map_err(|e| e.to_string())?;
+                Some(output)
+            }
+            None => None,
+        };
+        if let Some(directory) = &options.snapshots {
+            fs::create_dir_all(directory).map_err(|e| format!("Cannot create directory '{}' : {}", directory.display(), e))?;
+        }
+        let trajectory = match &options.trajectory {
+            Some(path) => Some(TrajectoryWriter::create(path, &world.current)?),
+            None => None,
+        };
+        let csv = match &options.csv {
+            Some(path) => Some(CsvExporter::create(path)?),
+            None => None,
+        };
+        Ok(Sinks {
+            metrics,
+            snapshots: options.snapshots.clone(),
+            trajectory,
+            csv,
+        })
+    }
+
+    fn write(&mut self, world: &mut World, step: usize, dt: f32) -> Result<(), String> {
+        let time = step as f32 * dt;
+        if let Some(output) = self.metrics.as_mut() {
+            let metrics = FlockMetrics::compute(world);
+            writeln!(output, "{},{},{}", step, time, metrics.csv_row()).map_err(|e| e.to_string())?;
+        }
+        if let Some(directory) = &self.snapshots {
+            snapshot::save(world, directory.join(format!("step_{:08}.snapshot", step)))?;
+        }
+        if let Some(trajectory) = self.trajectory.as_mut() {
+            trajectory.write_frame(time, &world.current)?;
+        }
+        if let Some(csv) = self.csv.as_mut() {
+            csv.export(world, step, dt)?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), String> {
+        if let Some(output) = self.metrics.as_mut() {
+            output.flush().map_err(|e| e.to_string())?;
+        }
+        if let Some(trajectory) = self.trajectory.as_mut() {
+            trajectory.flush()?;
+        }
+        if let Some(csv) = self.csv.as_mut() {
+            csv.flush()?;
+        }
+        Ok(())
+    }
+}
+
+fn run(options: &Options, headless: &HeadlessOptions) -> Result<(), String> {
+    let mut world = World::new(options.nb_birds, options.world_size);
+    if let Some(seed) = options.seed {
+        world.set_seed(seed);
+    }
+    options.apply_parameters(&mut world)?;
+    if let Some(path) = &options.scenario {
+        Scenario::load(path)?.apply(&mut world);
+    }
+    world.step_mode = headless.step_mode();
+    world.initialize();
+
+    let mut sinks = Sinks::create(headless, &world)?;
+    let mut computing = Duration::ZERO;
+    let start = Instant::now();
+    for step in 0..=headless.nb_steps {
+        if step % headless.output_interval == 0 {
+            sinks.write(&mut world, step, headless.dt)?;
+        }
+        if step < headless.nb_steps {
+            let step_start = Instant::now();
+            world.compute(headless.dt);
+            computing += step_start.elapsed();
+        }
+    }
+    sinks.flush()?;
+
+    let elapsed = start.elapsed().as_secs_f64();
+    let computing = computing.as_secs_f64();
+    println!(
+        "{} steps of {} boids in {:.2} s : {:.1} steps/s ({:.1} steps/s without the outputs), seed {}",
+        headless.nb_steps,
+        world.current.len(),
+        elapsed,
+        headless.nb_steps as f64 / elapsed.max(1e-9),
+        headless.nb_steps as f64 / computing.max(1e-9),
+        world.seed()
+    );
+    Ok(())
+}
+
+fn main() {
+    let mut headless = HeadlessOptions::new();
+    let options = match Options::parse_with(std::env::args().skip(1), |name, value| headless.parse_option(name, value)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, usage("boids-headless", &HeadlessOptions::usage()));
+            exit(1);
+        }
+    };
+    if options.help {
+        println!("{}", usage("boids-headless", &HeadlessOptions::usage()));
+        return;
+    }
+    if let Err(e) = run(&options, &headless) {
+        eprintln!("{}", e);
+        exit(1);
+    }
+}
@@ -0,0 +1,81 @@
+use rand::Rng;
+
+use crate::personality::Personality;
+use crate::vector::Vector;
+
+#[derive(Copy, Clone)]
+pub struct Boid {
+    pub position: Vector,
+    pub velocity: Vector,
+    pub species: usize,
+    pub personality: Personality,
+    /// acceleration of the last step with the Reynolds physics model
+    pub acceleration: Vector,
+    /// in degrees, positive when turning counter-clockwise
+    pub bank_angle: f32,
+    speed: f32,
+}
+
+impl Boid {
+    pub fn new() -> Self {
+        Boid {
+            position: Vector::new(),
+            velocity: Vector::new(),
+            species: 0,
+            personality: Personality::new(),
+            acceleration: Vector::new(),
+            bank_angle: 0.0,
+            speed: 0.0,
+        }
+    }
+
+    pub fn update_speed(&mut self) {
+        self.speed = self.velocity.x.hypot(self.velocity.y);
+    }
+
+    pub fn update_position(&mut self, dt: f32) {
+        self.position.add_scaled(&self.velocity, dt);
+    }
+
+    pub fn clamp_speed(&mut self, min_speed: f32, max_speed: f32, rng: &mut impl Rng) {
+        if self.speed > max_speed {
+            self.velocity.scale(max_speed / self.speed);
+            self.speed = max_speed;
+        }
+
+        if self.speed <= 1e-6 {
+            self.velocity.set_random(min_speed, rng);
+            self.speed = min_speed;
+        } else if self.speed < min_speed {
+            self.velocity.scale(min_speed / self.speed);
+            self.speed = min_speed;
+        }
+    }
+    /// Rotates the velocity back toward `previous` so it does not turn by more than `max_angle`
+    /// (in radians), keeping its norm. Returns the signed angle actually turned
+    pub fn limit_turn(&mut self, previous: &Vector, max_angle: f32) -> f32 {
+        let previous_speed = previous.hypot();
+        let speed = self.velocity.hypot();
+        if previous_speed <= 1e-6 || speed <= 1e-6 {
+            return 0.0;
+        }
+        let cross = previous.x * self.velocity.y - previous.y * self.velocity.x;
+        let dot = previous.x * self.velocity.x + previous.y * self.velocity.y;
+        let angle = cross.atan2(dot);
+        if angle.abs() <= max_angle {
+            return angle;
+        }
+
+        let angle = max_angle.copysign(angle);
+        let (sin, cos) = angle.sin_cos();
+        let factor = speed / previous_speed;
+        self.velocity.x = (previous.x * cos - previous.y * sin) * factor;
+        self.velocity.y = (previous.x * sin + previous.y * cos) * factor;
+        angle
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+}
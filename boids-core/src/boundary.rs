@@ -0,0 +1,261 @@
+use crate::boid::Boid;
+use crate::vector::Vector;
+
+pub const DEFAULT_STEER_STRENGTH: f32 = 0.1;
+pub const DEFAULT_STEER_MARGIN_X: f32 = 0.1;
+pub const DEFAULT_STEER_MARGIN_Y: f32 = 0.2;
+pub const DEFAULT_STEER_NUDGE: f32 = 0.1;
+
+/// How boids are kept inside the playfield. The playfield is the square
+/// `[-playfield_size, playfield_size]²` or, for `Circle`, the disk of radius `playfield_size`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum BoundaryMode {
+    /// the playfield is a torus : a boid leaving on one side comes back on the other
+    /// and distances are computed with the closest image of each boid
+    Wrap,
+    /// boids bounce elastically on the playfield sides
+    Reflect,
+    /// boids are pushed back when they are in the margin along the sides.
+    /// The margins are fractions of `playfield_size` along x and y, `strength` scales the push back
+    /// and `nudge` is a sideways push that makes the boids turn instead of stopping
+    Steer { strength: f32, margin_x: f32, margin_y: f32, nudge: f32 },
+    /// boids bounce elastically on a circle
+    Circle,
+}
+
+impl BoundaryMode {
+    pub fn steer() -> Self {
+        BoundaryMode::Steer {
+            strength: DEFAULT_STEER_STRENGTH,
+            margin_x: DEFAULT_STEER_MARGIN_X,
+            margin_y: DEFAULT_STEER_MARGIN_Y,
+            nudge: DEFAULT_STEER_NUDGE,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BoundaryMode::Wrap => "Wrap",
+            BoundaryMode::Reflect => "Reflect",
+            BoundaryMode::Steer { .. } => "Steer",
+            BoundaryMode::Circle => "Circle",
+        }
+    }
+
+    /// the next mode in the order Steer, Wrap, Reflect, Circle
+    pub fn cycle(&self) -> Self {
+        match self {
+            BoundaryMode::Steer { .. } => BoundaryMode::Wrap,
+            BoundaryMode::Wrap => BoundaryMode::Reflect,
+            BoundaryMode::Reflect => BoundaryMode::Circle,
+            BoundaryMode::Circle => BoundaryMode::steer(),
+        }
+    }
+
+    pub fn apply(&self, boid: &mut Boid, playfield_size: f32) {
+        match *self {
+            BoundaryMode::Wrap => wrap(boid, playfield_size),
+            BoundaryMode::Reflect => reflect(boid, playfield_size),
+            BoundaryMode::Steer { strength, margin_x, margin_y, nudge } => {
+                steer(boid, playfield_size, strength, margin_x, margin_y, nudge)
+            }
+            BoundaryMode::Circle => reflect_on_circle(boid, playfield_size),
+        }
+    }
+
+    /// Returns the position of the image of `other` closest to `reference`.
+    /// This is `other` itself except in `Wrap` mode
+    pub fn closest_image(&self, reference: &Vector, other: &Vector, playfield_size: f32) -> Vector {
+        let mut image = *other;
+        if let BoundaryMode::Wrap = self {
+            let period = 2.0 * playfield_size;
+            image.x += period * ((reference.x - other.x) / period).round();
+            image.y += period * ((reference.y - other.y) / period).round();
+        }
+        image
+    }
+
+    /// Fills `result` with the positions at which neighbours of `position` within `radius` must be
+    /// searched : `position` itself and, in `Wrap` mode, its images on the other side of the
+    /// playfield when it is closer than `radius` to a side.
+    pub fn query_positions(&self, position: &Vector, radius: f32, playfield_size: f32, result: &mut Vec<Vector>) {
+        result.clear();
+        result.push(*position);
+        if *self != BoundaryMode::Wrap {
+            return;
+        }
+        let period = 2.0 * playfield_size;
+        let shift = |coordinate: f32| -> f32 {
+            if coordinate > playfield_size - radius {
+                -period
+            } else if coordinate < -playfield_size + radius {
+                period
+            } else {
+                0.0
+            }
+        };
+        let dx = shift(position.x);
+        let dy = shift(position.y);
+        if dx != 0.0 {
+            result.push(Vector { x: position.x + dx, y: position.y });
+        }
+        if dy != 0.0 {
+            result.push(Vector { x: position.x, y: position.y + dy });
+        }
+        if dx != 0.0 && dy != 0.0 {
+            result.push(Vector { x: position.x + dx, y: position.y + dy });
+        }
+    }
+}
+
+fn wrap(boid: &mut Boid, playfield_size: f32) {
+    let period = 2.0 * playfield_size;
+    if boid.position.x >= playfield_size || boid.position.x < -playfield_size {
+        boid.position.x -= period * ((boid.position.x + playfield_size) / period).floor();
+    }
+    if boid.position.y >= playfield_size || boid.position.y < -playfield_size {
+        boid.position.y -= period * ((boid.position.y + playfield_size) / period).floor();
+    }
+}
+
+fn reflect(boid: &mut Boid, playfield_size: f32) {
+    if boid.position.x > playfield_size {
+        boid.position.x = (2.0 * playfield_size - boid.position.x).max(-playfield_size);
+        boid.velocity.x = -boid.velocity.x.abs();
+    } else if boid.position.x < -playfield_size {
+        boid.position.x = (-2.0 * playfield_size - boid.position.x).min(playfield_size);
+        boid.velocity.x = boid.velocity.x.abs();
+    }
+    if boid.position.y > playfield_size {
+        boid.position.y = (2.0 * playfield_size - boid.position.y).max(-playfield_size);
+        boid.velocity.y = -boid.velocity.y.abs();
+    } else if boid.position.y < -playfield_size {
+        boid.position.y = (-2.0 * playfield_size - boid.position.y).min(playfield_size);
+        boid.velocity.y = boid.velocity.y.abs();
+    }
+}
+
+fn steer(boid: &mut Boid, playfield_size: f32, strength: f32, margin_x: f32, margin_y: f32, nudge: f32) {
+    let limit_x = playfield_size * (1.0 - margin_x);
+    let limit_y = playfield_size * (1.0 - margin_y);
+
+    if boid.position.x > limit_x {
+        boid.velocity.x -= strength * (boid.position.x - limit_x);
+        boid.velocity.y += nudge;
+    }
+    if boid.position.x < -limit_x {
+        boid.velocity.x += strength * (-limit_x - boid.position.x);
+        boid.velocity.y -= nudge;
+    }
+    if boid.position.y > limit_y {
+        boid.velocity.y -= strength * (boid.position.y - limit_y);
+        boid.velocity.x += nudge;
+    }
+    if boid.position.y < -limit_y {
+        boid.velocity.y += strength * (-limit_y - boid.position.y);
+        boid.velocity.x -= nudge;
+    }
+}
+
+fn reflect_on_circle(boid: &mut Boid, radius: f32) {
+    let distance = boid.position.hypot();
+    if distance <= radius || distance == 0.0 {
+        return;
+    }
+    let normal = Vector {
+        x: boid.position.x / distance,
+        y: boid.position.y / distance,
+    };
+
+    let inside = (2.0 * radius - distance).max(0.0);
+    boid.position = normal;
+    boid.position.scale(inside);
+
+    let along_normal = boid.velocity.x * normal.x + boid.velocity.y * normal.y;
+    if along_normal > 0.0 {
+        boid.velocity.add_scaled(&normal, -2.0 * along_normal);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn boid(x: f32, y: f32, vx: f32, vy: f32) -> Boid {
+        let mut boid = Boid::new();
+        boid.position = Vector { x, y };
+        boid.velocity = Vector { x: vx, y: vy };
+        boid.update_speed();
+        boid
+    }
+
+    #[test]
+    fn wrap_moves_boids_to_the_other_side() {
+        let mut b = boid(10.5, -10.25, 1.0, -1.0);
+        BoundaryMode::Wrap.apply(&mut b, 10.0);
+        assert!((b.position.x + 9.5).abs() < 1e-5);
+        assert!((b.position.y - 9.75).abs() < 1e-5);
+        assert_eq!(b.velocity.x, 1.0);
+    }
+
+    #[test]
+    fn closest_image_uses_minimum_distance() {
+        let reference = Vector { x: 9.5, y: 0.0 };
+        let other = Vector { x: -9.5, y: 0.0 };
+        let image = BoundaryMode::Wrap.closest_image(&reference, &other, 10.0);
+        assert!((image.x - 10.5).abs() < 1e-5);
+        let image = BoundaryMode::Reflect.closest_image(&reference, &other, 10.0);
+        assert_eq!(image.x, -9.5);
+    }
+
+    #[test]
+    fn reflect_bounces_on_the_sides() {
+        let mut b = boid(10.5, 0.0, 2.0, 1.0);
+        BoundaryMode::Reflect.apply(&mut b, 10.0);
+        assert!((b.position.x - 9.5).abs() < 1e-5);
+        assert_eq!(b.velocity.x, -2.0);
+        assert_eq!(b.velocity.y, 1.0);
+    }
+
+    #[test]
+    fn circle_bounces_on_the_arena() {
+        let mut b = boid(0.0, 11.0, 1.0, 3.0);
+        BoundaryMode::Circle.apply(&mut b, 10.0);
+        assert!((b.position.y - 9.0).abs() < 1e-5);
+        assert_eq!(b.velocity.x, 1.0);
+        assert_eq!(b.velocity.y, -3.0);
+    }
+
+    /// the baseline `constraint_boid_rect`, limits at 0.9 and 0.8 of the playfield size
+    fn constraint_boid_rect(boid: &mut Boid, playfield_size: f32) {
+        let limitx = playfield_size * 0.9;
+        let limity = playfield_size * 0.8;
+        if boid.position.x > limitx {
+            boid.velocity.x -= 0.1 * (boid.position.x - limitx);
+            boid.velocity.y += 0.1;
+        }
+        if boid.position.x < -limitx {
+            boid.velocity.x += 0.1 * (-limitx - boid.position.x);
+            boid.velocity.y -= 0.1;
+        }
+        if boid.position.y > limity {
+            boid.velocity.y -= 0.1 * (boid.position.y - limity);
+            boid.velocity.x += 0.1;
+        }
+        if boid.position.y < -limity {
+            boid.velocity.y += 0.1 * (-limity - boid.position.y);
+            boid.velocity.x -= 0.1;
+        }
+    }
+
+    #[test]
+    fn default_steer_keeps_the_baseline_limits() {
+        for &(x, y) in [(8.5, 0.0), (9.5, 0.0), (0.0, 8.5), (0.0, -8.5), (-9.5, 7.5), (9.99, -9.99)].iter() {
+            let mut expected = boid(x, y, 1.0, -2.0);
+            constraint_boid_rect(&mut expected, 10.0);
+            let mut b = boid(x, y, 1.0, -2.0);
+            BoundaryMode::steer().apply(&mut b, 10.0);
+            assert_eq!(b.velocity, expected.velocity, "at ({}, {})", x, y);
+        }
+    }
+}
@@ -0,0 +1,367 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::snapshot;
+use crate::world::{Parameters, StepMode, World};
+
+pub const DEFAULT_NB_BIRDS: usize = 2000;
+pub const DEFAULT_WORLD_SIZE: f32 = 10.;
+pub const DEFAULT_NB_STEPS: usize = 1000;
+pub const DEFAULT_DT: f32 = 1.0 / 60.0;
+pub const DEFAULT_OUTPUT_INTERVAL: usize = 10;
+pub const DEFAULT_NB_THREADS: usize = 1;
+
+/// Settings of a run given on the command line
+#[derive(Clone, Debug, PartialEq)]
+pub struct Options {
+    pub nb_birds: usize,
+    pub world_size: f32,
+    /// random if `None`
+    pub seed: Option<u64>,
+    /// file of parameter values applied to every species, see `parse_preset`
+    pub preset: Option<PathBuf>,
+    pub scenario: Option<PathBuf>,
+    /// parameter values applied to every species after the preset, in order
+    pub parameters: Vec<(String, String)>,
+    pub help: bool,
+}
+
+impl Options {
+    pub fn new() -> Self {
+        Options {
+            nb_birds: DEFAULT_NB_BIRDS,
+            world_size: DEFAULT_WORLD_SIZE,
+            seed: None,
+            preset: None,
+            scenario: None,
+            parameters: vec![],
+            help: false,
+        }
+    }
+
+    /// Parses the arguments, without the name of the program. A parameter of the species is
+    /// given by its name as in a snapshot, with dashes or underscores : `--max-bird-speed 2`
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        Options::parse_with(args, |_, _| Ok(false))
+    }
+
+    /// Parses the arguments as `parse`, giving the other options, with their name in underscores
+    /// and their value, to `other`, which returns whether it knows the option
+    pub fn parse_with(
+        args: impl IntoIterator<Item = String>,
+        mut other: impl FnMut(&str, &str) -> Result<bool, String>,
+    ) -> Result<Self, String> {
+        let mut options = Options::new();
+        // parameters of the command line, checked now to report the errors with the argument
+        let mut parameters = Parameters::new();
+        let mut last_speed_arg = None;
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            if !arg.starts_with('-') {
+                if options.scenario.is_some() {
+                    return Err(format!("unexpected argument '{}'", arg));
+                }
+                options.scenario = Some(PathBuf::from(arg));
+                continue;
+            }
+            if arg == "-h" || arg == "--help" {
+                options.help = true;
+                continue;
+            }
+
+            let name = arg.trim_start_matches('-').replace('-', "_");
+            let value = args.next().ok_or_else(|| format!("{} : missing value", arg))?;
+            let invalid = |message: &str| format!("{} : {}", arg, message);
+            match name.as_str() {
+                "birds" => options.nb_birds = value.parse().map_err(|_| invalid(&format!("'{}' is not a number of birds", value)))?,
+                "size" => {
+                    options.world_size = value.parse().map_err(|_| invalid(&format!("'{}' is not a number", value)))?;
+                    if options.world_size <= 0.0 || !options.world_size.is_finite() {
+                        return Err(invalid("the size of the world must be positive"));
+                    }
+                }
+                "seed" => options.seed = Some(value.parse().map_err(|_| invalid(&format!("'{}' is not a seed", value)))?),
+                "preset" => options.preset = Some(PathBuf::from(value)),
+                "scenario" => options.scenario = Some(PathBuf::from(value)),
+                _ if snapshot::PARAMETER_NAMES.contains(&name.as_str()) => {
+                    snapshot::set_parameter(&mut parameters, &name, &value).map_err(|e| invalid(&e))?;
+                    if name.ends_with("bird_speed") {
+                        last_speed_arg = Some(arg.clone());
+                    }
+                    options.parameters.push((name, value));
+                }
+                _ => {
+                    if !other(&name, &value).map_err(|e| invalid(&e))? {
+                        return Err(format!("unknown option '{}'", arg));
+                    }
+                }
+            }
+        }
+        if let Some(arg) = last_speed_arg {
+            snapshot::check_parameters(&parameters).map_err(|e| format!("{} : {}", arg, e))?;
+        }
+        Ok(options)
+    }
+
+    /// Applies the preset, then the parameters of the command line, to every species of the world
+    pub fn apply_parameters(&self, world: &mut World) -> Result<(), String> {
+        let mut parameters = match &self.preset {
+            Some(path) => load_preset(path)?,
+            None => vec![],
+        };
+        parameters.extend_from_slice(&self.parameters);
+        for species in 0..world.nb_species() {
+            for (name, value) in parameters.iter() {
+                snapshot::set_parameter(world.species_parameters_mut(species), name, value)
+                    .map_err(|e| format!("{} : {}", name, e))?;
+            }
+            snapshot::check_parameters(world.species_parameters(species)).map_err(|e| format!("species {} : {}", species, e))?;
+        }
+        Ok(())
+    }
+}
+
+/// Help of the options, followed by `other_options`, the help of the options given to
+/// `Options::parse_with`, one per line
+pub fn usage(program: &str, other_options: &str) -> String {
+    format!(
+        "Usage : {} [options] [scenario]\n\
+         \n\
+         Options :\n\
+         \x20 --birds <n>          number of birds ({})\n\
+         \x20 --size <size>        half width of the playfield ({})\n\
+         \x20 --seed <seed>        seed of the random generator, random by default\n\
+         \x20 --preset <file>      parameters of the species, one '<name> <value>' per line\n\
+         \x20 --scenario <file>    obstacles, paths and flows\n\
+         \x20 --<parameter> <value>  parameter of every species, among :\n\
+         \x20     {}\n\
+         {}\
+         \x20 -h, --help           this help",
+        program,
+        DEFAULT_NB_BIRDS,
+        DEFAULT_WORLD_SIZE,
+        snapshot::PARAMETER_NAMES.join(", "),
+        other_options
+    )
+}
+
+/// Settings of a headless run, given on the command line with the options of the world
+#[derive(Clone, Debug, PartialEq)]
+pub struct HeadlessOptions {
+    pub nb_steps: usize,
+    pub dt: f32,
+    /// number of steps between two outputs
+    pub output_interval: usize,
+    /// the steps are computed sequentially with one thread, so the run does not depend on the machine
+    pub nb_threads: usize,
+    /// CSV file of the metrics
+    pub metrics: Option<PathBuf>,
+    /// directory of the snapshots
+    pub snapshots: Option<PathBuf>,
+    /// binary trajectory file
+    pub trajectory: Option<PathBuf>,
+    /// CSV file of the boids
+    pub csv: Option<PathBuf>,
+}
+
+impl HeadlessOptions {
+    pub fn new() -> Self {
+        HeadlessOptions {
+            nb_steps: DEFAULT_NB_STEPS,
+            dt: DEFAULT_DT,
+            output_interval: DEFAULT_OUTPUT_INTERVAL,
+            nb_threads: DEFAULT_NB_THREADS,
+            metrics: None,
+            snapshots: None,
+            trajectory: None,
+            csv: None,
+        }
+    }
+
+    /// sets the option `name` if it is an option of a headless run, see `Options::parse_with`
+    pub fn parse_option(&mut self, name: &str, value: &str) -> Result<bool, String> {
+        match name {
+            "steps" => self.nb_steps = value.parse().map_err(|_| format!("'{}' is not a number of steps", value))?,
+            "dt" => {
+                self.dt = value.parse().map_err(|_| format!("'{}' is not a number", value))?;
+                if self.dt <= 0.0 || !self.dt.is_finite() {
+                    return Err("the time step must be positive".to_string());
+                }
+            }
+            "interval" => {
+                self.output_interval = value.parse().map_err(|_| format!("'{}' is not a number of steps", value))?;
+                if self.output_interval == 0 {
+                    return Err("the interval must be at least one step".to_string());
+                }
+            }
+            "threads" => {
+                self.nb_threads = value.parse().map_err(|_| format!("'{}' is not a number of threads", value))?;
+                if self.nb_threads == 0 {
+                    return Err("at least one thread is needed".to_string());
+                }
+            }
+            "metrics" => self.metrics = Some(PathBuf::from(value)),
+            "snapshots" => self.snapshots = Some(PathBuf::from(value)),
+            "trajectory" => self.trajectory = Some(PathBuf::from(value)),
+            "csv" => self.csv = Some(PathBuf::from(value)),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    pub fn step_mode(&self) -> StepMode {
+        match self.nb_threads {
+            1 => StepMode::Sequential,
+            nb_threads => StepMode::Parallel { nb_threads },
+        }
+    }
+
+    pub fn usage() -> String {
+        format!(
+            "\x20 --steps <n>          number of steps ({})\n\
+             \x20 --dt <dt>            time step ({})\n\
+             \x20 --interval <n>       steps between two outputs ({})\n\
+             \x20 --threads <n>        threads computing the steps ({}), the result depends on it\n\
+             \x20 --metrics <file>     CSV time series of the flock metrics\n\
+             \x20 --snapshots <dir>    snapshots of the world\n\
+             \x20 --trajectory <file>  binary trajectory of the boids\n\
+             \x20 --csv <file>         CSV rows of the boids\n",
+            DEFAULT_NB_STEPS, DEFAULT_DT, DEFAULT_OUTPUT_INTERVAL, DEFAULT_NB_THREADS
+        )
+    }
+}
+
+pub fn load_preset(path: impl AsRef<Path>) -> Result<Vec<(String, String)>, String> {
+    let path = path.as_ref();
+    let content = fs::read_to_string(path).map_err(|e| format!("Cannot read preset '{}' : {}", path.display(), e))?;
+    parse_preset(&content).map_err(|e| format!("Invalid preset '{}' : {}", path.display(), e))
+}
+
+/// Parses a preset : one `<name> <value>` per line, with the parameter names and values of a snapshot.
+/// `#` starts a comment
+pub fn parse_preset(content: &str) -> Result<Vec<(String, String)>, String> {
+    let mut parameters = vec![];
+    for (line_index, line) in content.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        if line.is_empty() {
+            continue;
+        }
+        let (name, value) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let value = value.trim();
+        snapshot::set_parameter(&mut Parameters::new(), name, value).map_err(|e| format!("line {} : {}", line_index + 1, e))?;
+        parameters.push((name.to_string(), value.to_string()));
+    }
+    Ok(parameters)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::NeighbourMode;
+
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        Options::parse(args.iter().map(|a| a.to_string()))
+    }
+
+    #[test]
+    fn options_are_parsed() {
+        let options = parse(&["--birds", "50", "--max-bird-speed", "20", "--neighbour_mode", "topological 4", "scenario.txt"]).unwrap();
+        assert_eq!(options.nb_birds, 50);
+        assert_eq!(options.scenario, Some(PathBuf::from("scenario.txt")));
+
+        let mut world = World::new(options.nb_birds, options.world_size);
+        world.add_species(Parameters::new());
+        options.apply_parameters(&mut world).unwrap();
+        assert_eq!(world.species_parameters(1).max_bird_speed, 20.0);
+        assert_eq!(world.parameters.neighbour_mode, NeighbourMode::Topological { k: 4 });
+
+        assert_eq!(parse(&["--birds", "many"]).unwrap_err(), "--birds : 'many' is not a number of birds");
+        assert_eq!(parse(&["--size", "-1"]).unwrap_err(), "--size : the size of the world must be positive");
+        assert_eq!(parse(&["--window", "800x600"]).unwrap_err(), "unknown option '--window'");
+        assert_eq!(parse(&["--mass"]).unwrap_err(), "--mass : missing value");
+        assert_eq!(parse(&["--mass", "heavy"]).unwrap_err(), "--mass : 'heavy' is not a number");
+        assert_eq!(parse(&["--colour", "red"]).unwrap_err(), "unknown option '--colour'");
+        assert_eq!(parse(&["--interval", "10"]).unwrap_err(), "unknown option '--interval'");
+    }
+
+    #[test]
+    fn invalid_parameters_are_rejected() {
+        assert_eq!(
+            parse(&["--neighbour-mode", "topological 0"]).unwrap_err(),
+            "--neighbour-mode : the topological mode needs at least one neighbour"
+        );
+        assert_eq!(parse(&["--visibility-radius", "-5"]).unwrap_err(), "--visibility-radius : the radius must not be negative");
+        assert_eq!(parse(&["--flee-radius", "-1"]).unwrap_err(), "--flee-radius : the radius must not be negative");
+        assert_eq!(parse(&["--bird-size", "-0.1"]).unwrap_err(), "--bird-size : the size must not be negative");
+        assert_eq!(parse(&["--min-bird-speed", "-1"]).unwrap_err(), "--min-bird-speed : the speed must not be negative");
+        assert_eq!(parse(&["--max-bird-speed", "-1"]).unwrap_err(), "--max-bird-speed : the speed must not be negative");
+        assert_eq!(
+            parse(&["--safe-space-ratio", "1.5"]).unwrap_err(),
+            "--safe-space-ratio : the safe space ratio must be between 0 and 1"
+        );
+        assert_eq!(parse(&["--dead-angle", "400"]).unwrap_err(), "--dead-angle : the dead angle must be between 0 and 360");
+        assert_eq!(parse(&["--max-turn-rate", "-90"]).unwrap_err(), "--max-turn-rate : the turn rate must not be negative");
+        assert_eq!(
+            parse(&["--max-bird-speed", "3"]).unwrap_err(),
+            "--max-bird-speed : the minimal speed 5 is above the maximal speed 3"
+        );
+        // the speeds are compared once all of them are given
+        assert!(parse(&["--min-bird-speed", "20", "--max-bird-speed", "30"]).is_ok());
+        assert!(parse(&["--max-turn-rate", "inf", "--dead-angle", "360"]).is_ok());
+
+        let mut world = World::new(1, 1.0);
+        let options = Options { parameters: vec![("min_bird_speed".to_string(), "20".to_string())], ..Options::new() };
+        assert_eq!(
+            options.apply_parameters(&mut world).unwrap_err(),
+            "species 0 : the minimal speed 20 is above the maximal speed 16"
+        );
+    }
+
+    #[test]
+    fn headless_options_are_parsed() {
+        let parse_headless = |args: &[&str]| {
+            let mut headless = HeadlessOptions::new();
+            Options::parse_with(args.iter().map(|a| a.to_string()), |name, value| headless.parse_option(name, value))
+                .map(|options| (options, headless))
+        };
+        let (options, headless) = parse_headless(&["--birds", "50", "--steps", "20", "--csv", "boids.csv"]).unwrap();
+        assert_eq!((options.nb_birds, headless.nb_steps), (50, 20));
+        assert_eq!(headless.csv, Some(PathBuf::from("boids.csv")));
+        assert_eq!(headless.step_mode(), StepMode::Sequential);
+
+        let (_, headless) = parse_headless(&["--threads", "4"]).unwrap();
+        assert_eq!(headless.step_mode(), StepMode::Parallel { nb_threads: 4 });
+
+        assert_eq!(parse_headless(&["--threads", "0"]).unwrap_err(), "--threads : at least one thread is needed");
+        assert_eq!(parse_headless(&["--interval", "0"]).unwrap_err(), "--interval : the interval must be at least one step");
+        assert_eq!(parse_headless(&["--window", "800x600"]).unwrap_err(), "unknown option '--window'");
+    }
+
+    #[test]
+    fn other_options_are_given_to_the_binary() {
+        let mut window = None;
+        let args = ["--window-size", "800x600", "--birds", "50"].iter().map(|a| a.to_string());
+        let options = Options::parse_with(args, |name, value| match name {
+            "window_size" if value.contains('x') => {
+                window = Some(value.to_string());
+                Ok(true)
+            }
+            "window_size" => Err(format!("expected WIDTHxHEIGHT, got '{}'", value)),
+            _ => Ok(false),
+        })
+        .unwrap();
+        assert_eq!((options.nb_birds, window.as_deref()), (50, Some("800x600")));
+
+        let error = Options::parse_with(["--window-size", "800"].iter().map(|a| a.to_string()), |_, value| {
+            Err(format!("expected WIDTHxHEIGHT, got '{}'", value))
+        });
+        assert_eq!(error.unwrap_err(), "--window-size : expected WIDTHxHEIGHT, got '800'");
+    }
+
+    #[test]
+    fn preset_lists_parameters() {
+        let preset = parse_preset("# calm flock\ncohesion_factor 0.2\n\nboundary_mode steer 1 0.5 0.5 0.1\n").unwrap();
+        assert_eq!(preset[1], ("boundary_mode".to_string(), "steer 1 0.5 0.5 0.1".to_string()));
+        assert_eq!(parse_preset("cohesion_factor\n").unwrap_err(), "line 1 : missing value");
+    }
+}
@@ -0,0 +1,233 @@
+use std::fmt;
+
+use crate::world::World;
+
+/// Group of boids connected through the visibility graph
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cluster {
+    /// kept from one update to the other while the cluster keeps most of its members
+    pub id: usize,
+    /// indices of the boids, in increasing order
+    pub members: Vec<usize>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ClusterEvent {
+    Split { from: usize, into: Vec<usize> },
+    Merge { from: Vec<usize>, into: usize },
+}
+
+impl fmt::Display for ClusterEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClusterEvent::Split { from, into } => write!(f, "flock {} split into {:?}", from, into),
+            ClusterEvent::Merge { from, into } => write!(f, "flocks {:?} merged into {}", from, into),
+        }
+    }
+}
+
+/// Follows the flocks from one step to the other. Each new cluster takes the id of the previous
+/// cluster it shares the most members with, unless a cluster sharing more members already took it.
+/// Only the clusters sharing at least `min_size` members, or most of the members of the smaller
+/// of the two, are related, so a few boids changing flock are neither a split nor a merge
+pub struct ClusterTracker {
+    /// smaller connected components are not considered as flocks
+    pub min_size: usize,
+    clusters: Vec<Cluster>,
+    /// position in `clusters` of the cluster of every boid
+    membership: Vec<Option<usize>>,
+    next_id: usize,
+}
+
+impl ClusterTracker {
+    pub fn new(min_size: usize) -> Self {
+        ClusterTracker {
+            min_size,
+            clusters: vec![],
+            membership: vec![],
+            next_id: 0,
+        }
+    }
+
+    pub fn clusters(&self) -> &[Cluster] {
+        &self.clusters
+    }
+
+    /// id of the cluster of a boid, `None` if it is not part of a flock
+    pub fn cluster_of(&self, boid: usize) -> Option<usize> {
+        self.membership.get(boid).copied().flatten().map(|c| self.clusters[c].id)
+    }
+
+    /// Finds the clusters of the current state of the world and returns the splits and merges
+    /// since the last update
+    pub fn update(&mut self, world: &mut World) -> Vec<ClusterEvent> {
+        let components = connected_components(world.current.len(), &world.visible_pairs(), self.min_size);
+
+        // members shared by every new and previous cluster, (count, new, previous)
+        let mut overlaps = vec![];
+        for (new, members) in components.iter().enumerate() {
+            let mut counts = vec![0; self.clusters.len()];
+            for &boid in members.iter() {
+                if let Some(Some(previous)) = self.membership.get(boid) {
+                    counts[*previous] += 1;
+                }
+            }
+            for (previous, &count) in counts.iter().enumerate() {
+                let smaller = members.len().min(self.clusters[previous].members.len());
+                if count > 0 && (count >= self.min_size.max(1) || 2 * count > smaller) {
+                    overlaps.push((count, new, previous));
+                }
+            }
+        }
+        overlaps.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)).then(a.2.cmp(&b.2)));
+
+        let mut ids: Vec<Option<usize>> = vec![None; components.len()];
+        let mut taken = vec![false; self.clusters.len()];
+        for &(_, new, previous) in overlaps.iter() {
+            if ids[new].is_none() && !taken[previous] {
+                ids[new] = Some(self.clusters[previous].id);
+                taken[previous] = true;
+            }
+        }
+        let clusters: Vec<Cluster> = components
+            .into_iter()
+            .zip(ids)
+            .map(|(members, id)| {
+                let id = id.unwrap_or_else(|| {
+                    self.next_id += 1;
+                    self.next_id - 1
+                });
+                Cluster { id, members }
+            })
+            .collect();
+
+        let mut events = vec![];
+        for (previous, cluster) in self.clusters.iter().enumerate() {
+            let into = ids_of(overlaps.iter().filter(|o| o.2 == previous).map(|o| clusters[o.1].id));
+            if into.len() > 1 {
+                events.push(ClusterEvent::Split { from: cluster.id, into });
+            }
+        }
+        for (new, cluster) in clusters.iter().enumerate() {
+            let from = ids_of(overlaps.iter().filter(|o| o.1 == new).map(|o| self.clusters[o.2].id));
+            if from.len() > 1 {
+                events.push(ClusterEvent::Merge { from, into: cluster.id });
+            }
+        }
+
+        self.membership = vec![None; world.current.len()];
+        for (c, cluster) in clusters.iter().enumerate() {
+            for &boid in cluster.members.iter() {
+                self.membership[boid] = Some(c);
+            }
+        }
+        self.clusters = clusters;
+        events
+    }
+}
+
+fn ids_of(ids: impl Iterator<Item = usize>) -> Vec<usize> {
+    let mut ids: Vec<usize> = ids.collect();
+    ids.sort_unstable();
+    ids
+}
+
+/// Connected components of at least `min_size` nodes of the graph given by its edges,
+/// sorted by their smallest node
+pub fn connected_components(nb_nodes: usize, edges: &[(usize, usize)], min_size: usize) -> Vec<Vec<usize>> {
+    // union-find with path halving
+    let mut parents: Vec<usize> = (0..nb_nodes).collect();
+    fn root(parents: &mut [usize], mut node: usize) -> usize {
+        while parents[node] != node {
+            parents[node] = parents[parents[node]];
+            node = parents[node];
+        }
+        node
+    }
+    for &(a, b) in edges.iter() {
+        let (a, b) = (root(&mut parents, a), root(&mut parents, b));
+        if a != b {
+            parents[a.max(b)] = a.min(b);
+        }
+    }
+
+    let mut components: Vec<Vec<usize>> = vec![];
+    let mut component_of_root: Vec<Option<usize>> = vec![None; nb_nodes];
+    for node in 0..nb_nodes {
+        let r = root(&mut parents, node);
+        match component_of_root[r] {
+            Some(c) => components[c].push(node),
+            None => {
+                component_of_root[r] = Some(components.len());
+                components.push(vec![node]);
+            }
+        }
+    }
+    components.retain(|c| c.len() >= min_size.max(1));
+    components
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector::Vector;
+
+    #[test]
+    fn components_are_connected() {
+        let components = connected_components(7, &[(0, 2), (5, 2), (3, 4), (1, 1)], 2);
+        assert_eq!(components, vec![vec![0, 2, 5], vec![3, 4]]);
+    }
+
+    #[test]
+    fn ids_are_stable_through_splits_and_merges() {
+        // two groups flying toward each other along x, far apart
+        let mut world = World::new(6, 10.0);
+        for (i, boid) in world.current.iter_mut().enumerate() {
+            let side = if i < 3 { -1.0 } else { 1.0 };
+            boid.position = Vector { x: side * 3.0, y: 0.05 * i as f32 };
+            boid.velocity = Vector { x: -side, y: 0.0 };
+            boid.update_speed();
+        }
+        world.parameters.visibility_radius = 0.5;
+        let mut tracker = ClusterTracker::new(2);
+        assert!(tracker.update(&mut world).is_empty());
+        assert_eq!(tracker.clusters().len(), 2);
+        let (left, right) = (tracker.cluster_of(0).unwrap(), tracker.cluster_of(3).unwrap());
+        assert_ne!(left, right);
+
+        for boid in world.current.iter_mut() {
+            boid.position.x *= 0.01;
+        }
+        assert_eq!(tracker.update(&mut world), vec![ClusterEvent::Merge { from: vec![0, 1], into: left }]);
+        assert_eq!(tracker.cluster_of(5), Some(left));
+
+        for boid in world.current.iter_mut() {
+            boid.position.x *= 100.0;
+        }
+        assert_eq!(tracker.update(&mut world), vec![ClusterEvent::Split { from: left, into: vec![0, 2] }]);
+        assert_eq!(tracker.cluster_of(0), Some(left));
+        assert_eq!(tracker.cluster_of(3), Some(2));
+    }
+
+    #[test]
+    fn a_boid_changing_flock_is_no_event() {
+        // two lines of boids flying side by side, far apart
+        let mut world = World::new(12, 10.0);
+        for (i, boid) in world.current.iter_mut().enumerate() {
+            let side = if i < 6 { -1.0 } else { 1.0 };
+            boid.position = Vector { x: side * 3.0, y: 0.05 * (i % 6) as f32 };
+            boid.velocity = Vector { x: 1.0, y: 0.0 };
+            boid.update_speed();
+        }
+        world.parameters.visibility_radius = 0.5;
+        let mut tracker = ClusterTracker::new(3);
+        tracker.update(&mut world);
+        let (left, right) = (tracker.cluster_of(1).unwrap(), tracker.cluster_of(6).unwrap());
+
+        world.current[0].position = Vector { x: 3.0, y: 0.3 };
+        assert_eq!(tracker.update(&mut world), vec![]);
+        assert_eq!(tracker.clusters().len(), 2);
+        assert_eq!((tracker.cluster_of(1), tracker.cluster_of(6)), (Some(left), Some(right)));
+        assert_eq!(tracker.cluster_of(0), Some(right));
+    }
+}
@@ -0,0 +1,128 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use crate::world::World;
+
+/// Writes the state of the boids as CSV, one row per boid per sampled step :
+/// `step,time,id,x,y,vx,vy,speed`, followed by `<rule>_x,<rule>_y` for every steering rule
+/// if `steering` is set
+pub struct CsvExporter<W: Write> {
+    output: W,
+    /// minimal number of steps between two sampled steps
+    pub interval: usize,
+    /// ids of the exported boids, all of them if `None`
+    pub boids: Option<Vec<usize>>,
+    /// exports the contributions of the steering rules too
+    pub steering: bool,
+    last_step: Option<usize>,
+}
+
+impl CsvExporter<BufWriter<File>> {
+    pub fn create(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let file = File::create(path).map_err(|e| format!("Cannot create CSV file '{}' : {}", path.display(), e))?;
+        Ok(CsvExporter::new(BufWriter::new(file)))
+    }
+}
+
+impl<W: Write> CsvExporter<W> {
+    /// exports every boid at every step, without the steering
+    pub fn new(output: W) -> Self {
+        CsvExporter {
+            output,
+            interval: 1,
+            boids: None,
+            steering: false,
+            last_step: None,
+        }
+    }
+
+    /// Writes the rows of the world at `step`, at time `step * dt`, if it is sampled, that is, the
+    /// first one or at least `interval` steps after the last sampled one. The steering is the one
+    /// of the next step of `dt`. Returns whether rows were written
+    pub fn export(&mut self, world: &mut World, step: usize, dt: f32) -> Result<bool, String> {
+        if self.last_step.is_some_and(|last| step < last + self.interval.max(1)) {
+            return Ok(false);
+        }
+        if self.last_step.is_none() {
+            self.write_header(world)?;
+        }
+        self.last_step = Some(step);
+
+        let ids: Vec<usize> = match &self.boids {
+            Some(boids) => boids.iter().copied().filter(|&id| id < world.current.len()).collect(),
+            None => (0..world.current.len()).collect(),
+        };
+        let steering = if self.steering { world.compute_steering(&ids, dt) } else { vec![] };
+
+        let mut rows = String::new();
+        for (i, &id) in ids.iter().enumerate() {
+            let boid = &world.current[id];
+            rows.push_str(&format!(
+                "{},{},{},{},{},{},{},{}",
+                step,
+                step as f32 * dt,
+                id,
+                boid.position.x,
+                boid.position.y,
+                boid.velocity.x,
+                boid.velocity.y,
+                boid.speed()
+            ));
+            if let Some(contributions) = steering.get(i) {
+                for contribution in contributions.iter() {
+                    rows.push_str(&format!(",{},{}", contribution.x, contribution.y));
+                }
+            }
+            rows.push('\n');
+        }
+        self.output.write_all(rows.as_bytes()).map_err(|e| e.to_string())?;
+        Ok(true)
+    }
+
+    pub fn flush(&mut self) -> Result<(), String> {
+        self.output.flush().map_err(|e| e.to_string())
+    }
+
+    fn write_header(&mut self, world: &World) -> Result<(), String> {
+        let mut header = "step,time,id,x,y,vx,vy,speed".to_string();
+        if self.steering {
+            for rule in world.rules.iter() {
+                let name = rule.name().to_lowercase().replace(' ', "_");
+                header.push_str(&format!(",{}_x,{}_y", name, name));
+            }
+        }
+        header.push('\n');
+        self.output.write_all(header.as_bytes()).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rows_are_sampled() {
+        let mut world = World::new(20, 2.0).with_seed(5);
+        world.initialize();
+        let mut exporter = CsvExporter::new(vec![]);
+        exporter.interval = 2;
+        exporter.boids = Some(vec![3, 7, 50]);
+        exporter.steering = true;
+
+        for step in 0..5 {
+            exporter.export(&mut world, step, 0.1).unwrap();
+            world.compute(0.1);
+        }
+
+        let csv = String::from_utf8(exporter.output).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert!(lines[0].starts_with("step,time,id,x,y,vx,vy,speed,separation_x,separation_y,"));
+        assert!(lines[0].ends_with(",path_following_x,path_following_y"));
+        // steps 0, 2 and 4, boids 3 and 7
+        assert_eq!(lines.len(), 1 + 3 * 2);
+        assert!(lines[3].starts_with("2,0.2,3,"));
+        assert!(lines.iter().all(|l| l.split(',').count() == 8 + 2 * world.rules.len()));
+    }
+}
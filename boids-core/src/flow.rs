@@ -0,0 +1,196 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::vector::Vector;
+
+/// Wind or current carrying the boids along
+#[derive(Clone, Debug, PartialEq)]
+pub enum FlowField {
+    Uniform { velocity: Vector },
+    /// Rankine vortex turning counter-clockwise for a positive strength. The speed of the flow grows
+    /// linearly up to `strength` at `radius` from the center, then decreases as the inverse of the distance
+    Vortex { center: Vector, strength: f32, radius: f32 },
+    Grid(FlowGrid),
+}
+
+impl FlowField {
+    pub fn uniform(x: f32, y: f32) -> Self {
+        FlowField::Uniform { velocity: Vector { x, y } }
+    }
+
+    pub fn vortex(x: f32, y: f32, strength: f32, radius: f32) -> Self {
+        FlowField::Vortex {
+            center: Vector { x, y },
+            strength,
+            radius,
+        }
+    }
+
+    /// velocity of the flow at `position`
+    pub fn sample(&self, position: &Vector) -> Vector {
+        match self {
+            FlowField::Uniform { velocity } => *velocity,
+            FlowField::Vortex { center, strength, radius } => {
+                let mut offset = *position;
+                offset.subtract(center);
+                let distance = offset.hypot();
+                if distance <= 1e-6 || *radius <= 0.0 {
+                    return Vector::new();
+                }
+                let speed = if distance < *radius {
+                    strength * distance / radius
+                } else {
+                    strength * radius / distance
+                };
+                Vector {
+                    x: -offset.y * speed / distance,
+                    y: offset.x * speed / distance,
+                }
+            }
+            FlowField::Grid(grid) => grid.sample(position),
+        }
+    }
+}
+
+/// Flow velocities given at the nodes of a regular grid, bilinearly interpolated in between.
+/// Outside of the grid, the velocity is the one of the closest border
+#[derive(Clone, Debug, PartialEq)]
+pub struct FlowGrid {
+    /// position of the first node
+    pub origin: Vector,
+    pub cell_size: f32,
+    nb_columns: usize,
+    nb_rows: usize,
+    /// row by row, from the lowest y
+    velocities: Vec<Vector>,
+    /// file the grid was loaded from, if any
+    pub source: Option<PathBuf>,
+}
+
+impl FlowGrid {
+    pub fn new(origin: Vector, cell_size: f32, nb_columns: usize, velocities: Vec<Vector>) -> Result<Self, String> {
+        if cell_size <= 0.0 {
+            return Err("the cell size of a flow grid must be positive".to_string());
+        }
+        if nb_columns == 0 || velocities.is_empty() || velocities.len() % nb_columns != 0 {
+            return Err(format!(
+                "{} velocities cannot fill rows of {} columns",
+                velocities.len(),
+                nb_columns
+            ));
+        }
+        Ok(FlowGrid {
+            origin,
+            cell_size,
+            nb_columns,
+            nb_rows: velocities.len() / nb_columns,
+            velocities,
+            source: None,
+        })
+    }
+
+    /// Loads a grid from a CSV file, `#` starting a comment. The first line is `x_min,y_min,cell_size`,
+    /// each following line is a row of the grid, from the lowest y, given as `vx,vy` pairs.
+    /// The source of the grid is the absolute path of the file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path).map_err(|e| format!("Cannot read flow grid '{}' : {}", path.display(), e))?;
+        let mut grid = FlowGrid::parse(&content).map_err(|e| format!("Invalid flow grid '{}' : {}", path.display(), e))?;
+        grid.source = Some(fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf()));
+        Ok(grid)
+    }
+
+    pub fn parse(content: &str) -> Result<Self, String> {
+        let mut header: Option<Vec<f32>> = None;
+        let mut nb_columns = 0;
+        let mut velocities = vec![];
+        for (line_index, line) in content.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let values = line
+                .split(',')
+                .map(|t| t.trim().parse::<f32>().map_err(|_| format!("line {} : '{}' is not a number", line_index + 1, t.trim())))
+                .collect::<Result<Vec<f32>, String>>()?;
+
+            if header.is_none() {
+                if values.len() != 3 {
+                    return Err(format!("line {} : expected x_min,y_min,cell_size", line_index + 1));
+                }
+                header = Some(values);
+                continue;
+            }
+            if values.len() % 2 != 0 || (nb_columns != 0 && values.len() != 2 * nb_columns) {
+                return Err(format!("line {} : every row needs the same number of vx,vy pairs", line_index + 1));
+            }
+            nb_columns = values.len() / 2;
+            velocities.extend(values.chunks(2).map(|c| Vector { x: c[0], y: c[1] }));
+        }
+
+        let header = header.ok_or_else(|| "the flow grid is empty".to_string())?;
+        FlowGrid::new(Vector { x: header[0], y: header[1] }, header[2], nb_columns, velocities)
+    }
+
+    pub fn nb_columns(&self) -> usize {
+        self.nb_columns
+    }
+
+    pub fn nb_rows(&self) -> usize {
+        self.nb_rows
+    }
+
+    pub fn sample(&self, position: &Vector) -> Vector {
+        let (column, u) = self.split((position.x - self.origin.x) / self.cell_size, self.nb_columns);
+        let (row, v) = self.split((position.y - self.origin.y) / self.cell_size, self.nb_rows);
+        let next_column = (column + 1).min(self.nb_columns - 1);
+        let next_row = (row + 1).min(self.nb_rows - 1);
+
+        let node = |c: usize, r: usize| self.velocities[r * self.nb_columns + c];
+        let mut result = Vector::new();
+        result.add_scaled(&node(column, row), (1.0 - u) * (1.0 - v));
+        result.add_scaled(&node(next_column, row), u * (1.0 - v));
+        result.add_scaled(&node(column, next_row), (1.0 - u) * v);
+        result.add_scaled(&node(next_column, next_row), u * v);
+        result
+    }
+
+    /// the node before a coordinate, in cell units, and the fraction of cell after it
+    fn split(&self, coordinate: f32, nb_nodes: usize) -> (usize, f32) {
+        let coordinate = coordinate.clamp(0.0, (nb_nodes - 1) as f32);
+        let node = (coordinate.floor() as usize).min(nb_nodes - 1);
+        (node, coordinate - node as f32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vortex_turns_counter_clockwise() {
+        let vortex = FlowField::vortex(1.0, 1.0, 2.0, 1.0);
+        let inside = vortex.sample(&Vector { x: 1.5, y: 1.0 });
+        assert!((inside.y - 1.0).abs() < 1e-6 && inside.x.abs() < 1e-6);
+        let outside = vortex.sample(&Vector { x: 1.0, y: 3.0 });
+        assert!((outside.x + 1.0).abs() < 1e-6 && outside.y.abs() < 1e-6);
+    }
+
+    #[test]
+    fn grid_is_bilinearly_interpolated() {
+        let grid = FlowGrid::parse(
+            "# origin and cell size\n\
+             -1,0,2\n\
+             0,0, 2,0\n\
+             0,4, 2,4\n",
+        )
+        .unwrap();
+        assert_eq!((grid.nb_columns(), grid.nb_rows()), (2, 2));
+
+        assert_eq!(grid.sample(&Vector { x: 0.0, y: 1.0 }), Vector { x: 1.0, y: 2.0 });
+        assert_eq!(grid.sample(&Vector { x: -5.0, y: 10.0 }), Vector { x: 0.0, y: 4.0 });
+
+        assert!(FlowGrid::parse("0,0,1\n1,0,1\n1,0\n").is_err());
+        assert!(FlowGrid::parse("0,0\n").is_err());
+    }
+}
@@ -0,0 +1,178 @@
+use crate::boid::Boid;
+use crate::spatial_index::{copy_positions, distance2, is_within, KNearest, SpatialIndex};
+use crate::vector::Vector;
+
+// upper bound on the number of cells along one axis, to keep memory bounded
+// when a few birds wander far away from the flock
+const MAX_CELLS_PER_AXIS: usize = 1024;
+
+/// Uniform grid bucketing the boids by position.
+/// Boids indices are stored cell by cell (counting sort) so
+/// a cell is a contiguous slice of `indices`.
+pub struct Grid {
+    cell_size: f32,
+    min_x: f32,
+    min_y: f32,
+    nb_columns: usize,
+    nb_rows: usize,
+    cell_start: Vec<usize>,
+    indices: Vec<usize>,
+    positions: Vec<Vector>,
+}
+
+impl Grid {
+    pub fn new() -> Self {
+        Grid {
+            cell_size: 1.0,
+            min_x: 0.0,
+            min_y: 0.0,
+            nb_columns: 0,
+            nb_rows: 0,
+            cell_start: vec![0],
+            indices: vec![],
+            positions: vec![],
+        }
+    }
+
+    /// Fills `result` with the indices (in increasing order) of the boids
+    /// that lie in the cells overlapping the square of half side `radius` centered on `position`.
+    /// This is a superset of the boids within `radius` of `position`.
+    pub fn candidates(&self, position: &Vector, radius: f32, result: &mut Vec<usize>) {
+        result.clear();
+        if self.nb_columns == 0 {
+            return;
+        }
+
+        let (column_min, row_min) = self.clamped_cell(position.x - radius, position.y - radius);
+        let (column_max, row_max) = self.clamped_cell(position.x + radius, position.y + radius);
+
+        for row in row_min..=row_max {
+            let start = self.cell_start[row * self.nb_columns + column_min];
+            let end = self.cell_start[row * self.nb_columns + column_max + 1];
+            result.extend_from_slice(&self.indices[start..end]);
+        }
+        result.sort_unstable();
+    }
+
+    fn cell_index(&self, position: &Vector) -> usize {
+        let (column, row) = self.clamped_cell(position.x, position.y);
+        row * self.nb_columns + column
+    }
+
+    fn clamped_cell(&self, x: f32, y: f32) -> (usize, usize) {
+        let column = ((x - self.min_x) / self.cell_size).max(0.0) as usize;
+        let row = ((y - self.min_y) / self.cell_size).max(0.0) as usize;
+        (column.min(self.nb_columns - 1), row.min(self.nb_rows - 1))
+    }
+
+    fn cell(&self, column: usize, row: usize) -> &[usize] {
+        let cell = row * self.nb_columns + column;
+        &self.indices[self.cell_start[cell]..self.cell_start[cell + 1]]
+    }
+
+    /// distance between `position` and the cell (column,row), 0 if inside
+    fn distance_to_cell(&self, position: &Vector, column: usize, row: usize) -> f32 {
+        let x_min = self.min_x + column as f32 * self.cell_size;
+        let y_min = self.min_y + row as f32 * self.cell_size;
+        let dx = (x_min - position.x).max(0.0).max(position.x - x_min - self.cell_size);
+        let dy = (y_min - position.y).max(0.0).max(position.y - y_min - self.cell_size);
+        dx.hypot(dy)
+    }
+}
+
+impl SpatialIndex for Grid {
+    fn rebuild(&mut self, boids: &[Boid], cell_size: f32) {
+        copy_positions(boids, &mut self.positions);
+        self.indices.clear();
+        self.indices.resize(boids.len(), 0);
+
+        if boids.is_empty() {
+            self.nb_columns = 0;
+            self.nb_rows = 0;
+            self.cell_start.clear();
+            self.cell_start.push(0);
+            return;
+        }
+
+        let mut min = boids[0].position;
+        let mut max = boids[0].position;
+        for boid in boids.iter() {
+            min.x = min.x.min(boid.position.x);
+            min.y = min.y.min(boid.position.y);
+            max.x = max.x.max(boid.position.x);
+            max.y = max.y.max(boid.position.y);
+        }
+
+        let extent = (max.x - min.x).max(max.y - min.y);
+        self.cell_size = cell_size
+            .max(extent / MAX_CELLS_PER_AXIS as f32)
+            .max(1e-6);
+        self.min_x = min.x;
+        self.min_y = min.y;
+        self.nb_columns = ((max.x - min.x) / self.cell_size) as usize + 1;
+        self.nb_rows = ((max.y - min.y) / self.cell_size) as usize + 1;
+
+        let nb_cells = self.nb_columns * self.nb_rows;
+        self.cell_start.clear();
+        self.cell_start.resize(nb_cells + 1, 0);
+
+        for boid in boids.iter() {
+            let cell = self.cell_index(&boid.position);
+            self.cell_start[cell + 1] += 1;
+        }
+        for cell in 0..nb_cells {
+            self.cell_start[cell + 1] += self.cell_start[cell];
+        }
+
+        let mut fill = self.cell_start.clone();
+        for (i, boid) in boids.iter().enumerate() {
+            let cell = self.cell_index(&boid.position);
+            self.indices[fill[cell]] = i;
+            fill[cell] += 1;
+        }
+    }
+
+    fn within_radius(&self, position: &Vector, radius: f32, result: &mut Vec<usize>) {
+        self.candidates(position, radius, result);
+        result.retain(|i| is_within(position, &self.positions[*i], radius));
+    }
+
+    fn nearest(&self, position: &Vector, k: usize, result: &mut Vec<usize>) {
+        let mut nearest = KNearest::new(k);
+        if self.nb_columns == 0 || k == 0 {
+            nearest.fill(result);
+            return;
+        }
+
+        let (column, row) = self.clamped_cell(position.x, position.y);
+        let offset = self.distance_to_cell(position, column, row);
+        let max_ring = self.nb_columns.max(self.nb_rows);
+
+        // visit the cells ring by ring around the cell of the position
+        for ring in 0..max_ring {
+            let column_min = column.saturating_sub(ring);
+            let row_min = row.saturating_sub(ring);
+            let column_max = (column + ring).min(self.nb_columns - 1);
+            let row_max = (row + ring).min(self.nb_rows - 1);
+
+            for r in row_min..=row_max {
+                for c in column_min..=column_max {
+                    let on_ring = r + ring == row || r == row + ring || c + ring == column || c == column + ring;
+                    if !on_ring {
+                        continue;
+                    }
+                    for i in self.cell(c, r) {
+                        nearest.offer(distance2(position, &self.positions[*i]), *i);
+                    }
+                }
+            }
+
+            // cells on the next ring are at least at this distance
+            let next_ring_distance = (ring as f32 * self.cell_size - offset).max(0.0);
+            if nearest.is_full() && nearest.worst_distance2() < next_ring_distance * next_ring_distance {
+                break;
+            }
+        }
+        nearest.fill(result);
+    }
+}
@@ -0,0 +1,107 @@
+use std::cmp::Ordering;
+
+use crate::boid::Boid;
+use crate::spatial_index::{copy_positions, distance2, is_within, pruning_distance2, KNearest, SpatialIndex};
+use crate::vector::Vector;
+
+/// Implicit 2d-tree : the median of the range `[start,end)` of `indices`
+/// is the node splitting the range, alternating the x and y axis with the depth.
+pub struct KdTree {
+    indices: Vec<usize>,
+    positions: Vec<Vector>,
+}
+
+fn coordinate(position: &Vector, axis: usize) -> f32 {
+    if axis == 0 {
+        position.x
+    } else {
+        position.y
+    }
+}
+
+impl KdTree {
+    pub fn new() -> Self {
+        KdTree {
+            indices: vec![],
+            positions: vec![],
+        }
+    }
+
+    fn build(&mut self, start: usize, end: usize, axis: usize) {
+        if end - start <= 1 {
+            return;
+        }
+        let middle = (start + end) / 2;
+        let positions = &self.positions;
+        self.indices[start..end].select_nth_unstable_by(middle - start, |a, b| {
+            coordinate(&positions[*a], axis)
+                .partial_cmp(&coordinate(&positions[*b], axis))
+                .unwrap_or(Ordering::Equal)
+        });
+        self.build(start, middle, 1 - axis);
+        self.build(middle + 1, end, 1 - axis);
+    }
+
+    fn collect_within(&self, start: usize, end: usize, axis: usize, position: &Vector, radius: f32, result: &mut Vec<usize>) {
+        if start >= end {
+            return;
+        }
+        let middle = (start + end) / 2;
+        let index = self.indices[middle];
+        if is_within(position, &self.positions[index], radius) {
+            result.push(index);
+        }
+
+        let delta = coordinate(position, axis) - coordinate(&self.positions[index], axis);
+        let crossing = delta * delta <= pruning_distance2(radius);
+        if delta <= 0.0 || crossing {
+            self.collect_within(start, middle, 1 - axis, position, radius, result);
+        }
+        if delta >= 0.0 || crossing {
+            self.collect_within(middle + 1, end, 1 - axis, position, radius, result);
+        }
+    }
+
+    fn collect_nearest(&self, start: usize, end: usize, axis: usize, position: &Vector, nearest: &mut KNearest) {
+        if start >= end {
+            return;
+        }
+        let middle = (start + end) / 2;
+        let index = self.indices[middle];
+        nearest.offer(distance2(position, &self.positions[index]), index);
+
+        let delta = coordinate(position, axis) - coordinate(&self.positions[index], axis);
+        let (near, far) = if delta <= 0.0 {
+            ((start, middle), (middle + 1, end))
+        } else {
+            ((middle + 1, end), (start, middle))
+        };
+        self.collect_nearest(near.0, near.1, 1 - axis, position, nearest);
+        if delta * delta <= nearest.worst_distance2() {
+            self.collect_nearest(far.0, far.1, 1 - axis, position, nearest);
+        }
+    }
+}
+
+impl SpatialIndex for KdTree {
+    fn rebuild(&mut self, boids: &[Boid], _radius: f32) {
+        copy_positions(boids, &mut self.positions);
+        self.indices.clear();
+        self.indices.extend(0..boids.len());
+        self.build(0, boids.len(), 0);
+    }
+
+    fn within_radius(&self, position: &Vector, radius: f32, result: &mut Vec<usize>) {
+        result.clear();
+        self.collect_within(0, self.indices.len(), 0, position, radius, result);
+        result.sort_unstable();
+    }
+
+    fn nearest(&self, position: &Vector, k: usize, result: &mut Vec<usize>) {
+        let mut nearest = KNearest::new(k);
+        if k > 0 {
+            self.collect_nearest(0, self.indices.len(), 0, position, &mut nearest);
+        }
+        nearest.fill(result);
+    }
+}
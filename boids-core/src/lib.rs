@@ -0,0 +1,28 @@
+#![allow(clippy::new_without_default)]
+
+pub mod attractor;
+pub mod boid;
+pub mod boundary;
+pub mod cli;
+pub mod cluster;
+pub mod export;
+pub mod flow;
+pub mod grid;
+pub mod kdtree;
+pub mod metrics;
+pub mod obstacle;
+pub mod path;
+pub mod personality;
+pub mod predator;
+pub mod quadtree;
+pub mod recording;
+pub mod rules;
+pub mod scenario;
+pub mod snapshot;
+pub mod spatial_index;
+pub mod species;
+pub mod steering;
+pub mod stepper;
+pub mod trajectory;
+pub mod vector;
+pub mod world;
@@ -0,0 +1,194 @@
+use crate::kdtree::KdTree;
+use crate::spatial_index::SpatialIndex;
+use crate::vector::Vector;
+use crate::world::World;
+
+/// Order parameters of the collective motion of the boids.
+/// The nearest neighbour distances go through the boundary in `Wrap` mode,
+/// the centroid, milling and extent are computed in playfield coordinates
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FlockMetrics {
+    pub nb_boids: usize,
+    /// norm of the mean unit heading : 1 when all the boids fly in the same direction
+    pub polarization: f32,
+    /// norm of the mean angular momentum of the unit headings around the centroid :
+    /// 1 when the boids turn around it in the same direction
+    pub milling: f32,
+    pub mean_nearest_distance: f32,
+    pub min_nearest_distance: f32,
+    pub mean_speed: f32,
+    pub speed_std_dev: f32,
+    pub min_speed: f32,
+    pub max_speed: f32,
+    pub centroid: Vector,
+    /// largest distance between a boid and the centroid
+    pub extent: f32,
+}
+
+/// columns of `FlockMetrics::csv_row`
+pub const CSV_HEADER: &str = "nb_boids,polarization,milling,mean_nearest_distance,min_nearest_distance,\
+mean_speed,speed_std_dev,min_speed,max_speed,centroid_x,centroid_y,extent";
+
+impl FlockMetrics {
+    /// the metrics of no boid at all
+    pub fn new() -> Self {
+        FlockMetrics {
+            nb_boids: 0,
+            polarization: 0.0,
+            milling: 0.0,
+            mean_nearest_distance: 0.0,
+            min_nearest_distance: 0.0,
+            mean_speed: 0.0,
+            speed_std_dev: 0.0,
+            min_speed: 0.0,
+            max_speed: 0.0,
+            centroid: Vector::new(),
+            extent: 0.0,
+        }
+    }
+
+    /// metrics of the current state of the world
+    pub fn compute(world: &World) -> Self {
+        let boids = &world.current;
+        let mut metrics = FlockMetrics::new();
+        if boids.is_empty() {
+            return metrics;
+        }
+        let nb_boids = boids.len() as f32;
+        metrics.nb_boids = boids.len();
+
+        let mut heading = Vector::new();
+        metrics.min_speed = f32::INFINITY;
+        for boid in boids.iter() {
+            let speed = boid.velocity.hypot();
+            if speed > 1e-6 {
+                heading.add_scaled(&boid.velocity, 1.0 / speed);
+            }
+            metrics.centroid.add_scaled(&boid.position, 1.0 / nb_boids);
+            metrics.mean_speed += speed / nb_boids;
+            metrics.min_speed = metrics.min_speed.min(speed);
+            metrics.max_speed = metrics.max_speed.max(speed);
+        }
+        metrics.polarization = heading.hypot() / nb_boids;
+
+        let mut momentum = 0.0;
+        let mut variance = 0.0;
+        for boid in boids.iter() {
+            let speed = boid.velocity.hypot();
+            variance += (speed - metrics.mean_speed).powi(2) / nb_boids;
+            let mut offset = boid.position;
+            offset.subtract(&metrics.centroid);
+            let distance = offset.hypot();
+            metrics.extent = metrics.extent.max(distance);
+            if distance > 1e-6 && speed > 1e-6 {
+                momentum += (offset.x * boid.velocity.y - offset.y * boid.velocity.x) / (distance * speed);
+            }
+        }
+        metrics.milling = momentum.abs() / nb_boids;
+        metrics.speed_std_dev = variance.sqrt();
+
+        if boids.len() > 1 {
+            let distances = nearest_distances(world);
+            metrics.mean_nearest_distance = distances.iter().sum::<f32>() / nb_boids;
+            metrics.min_nearest_distance = distances.iter().copied().fold(f32::INFINITY, f32::min);
+        }
+        metrics
+    }
+
+    /// the metrics as a CSV row, without line feed
+    pub fn csv_row(&self) -> String {
+        format!(
+            "{},{},{},{},{},{},{},{},{},{},{},{}",
+            self.nb_boids,
+            self.polarization,
+            self.milling,
+            self.mean_nearest_distance,
+            self.min_nearest_distance,
+            self.mean_speed,
+            self.speed_std_dev,
+            self.min_speed,
+            self.max_speed,
+            self.centroid.x,
+            self.centroid.y,
+            self.extent
+        )
+    }
+}
+
+/// distance from every boid to its nearest neighbour, through the boundary
+fn nearest_distances(world: &World) -> Vec<f32> {
+    let boids = &world.current;
+    let boundary_mode = world.parameters.boundary_mode;
+    let mut index = KdTree::new();
+    index.rebuild(boids, world.parameters.visibility_radius);
+
+    let mut nearest = Vec::new();
+    let mut images = Vec::new();
+    let mut candidates = Vec::new();
+    let distance = |i: usize, j: usize| -> f32 {
+        let mut offset = boids[i].position;
+        offset.subtract(&boundary_mode.closest_image(&boids[i].position, &boids[j].position, world.playfield_size));
+        offset.hypot()
+    };
+    (0..boids.len())
+        .map(|i| {
+            let position = boids[i].position;
+            index.nearest(&position, 2, &mut nearest);
+            let mut result = nearest.iter().filter(|&&j| j != i).map(|&j| distance(i, j)).fold(f32::INFINITY, f32::min);
+            // a closer boid may be on the other side of the playfield
+            boundary_mode.query_positions(&position, result, world.playfield_size, &mut images);
+            for image in images.iter().skip(1) {
+                index.within_radius(image, result, &mut candidates);
+                for &j in candidates.iter().filter(|&&j| j != i) {
+                    result = result.min(distance(i, j));
+                }
+            }
+            result
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::boundary::BoundaryMode;
+
+    fn world_with(boids: &[(f32, f32, f32, f32)]) -> World {
+        let mut world = World::new(boids.len(), 10.0);
+        for (boid, &(x, y, vx, vy)) in world.current.iter_mut().zip(boids.iter()) {
+            boid.position = Vector { x, y };
+            boid.velocity = Vector { x: vx, y: vy };
+        }
+        world
+    }
+
+    #[test]
+    fn aligned_boids_are_polarized() {
+        let world = world_with(&[(0.0, 0.0, 1.0, 0.0), (1.0, 0.0, 2.0, 0.0), (3.0, 0.0, 3.0, 0.0)]);
+        let metrics = FlockMetrics::compute(&world);
+        assert!((metrics.polarization - 1.0).abs() < 1e-6);
+        assert!(metrics.milling < 1e-6);
+        assert_eq!(metrics.min_nearest_distance, 1.0);
+        assert!((metrics.mean_nearest_distance - 4.0 / 3.0).abs() < 1e-6);
+        assert_eq!((metrics.min_speed, metrics.mean_speed, metrics.max_speed), (1.0, 2.0, 3.0));
+        assert!((metrics.extent - 5.0 / 3.0).abs() < 1e-6);
+        assert_eq!(metrics.csv_row().split(',').count(), CSV_HEADER.split(',').count());
+    }
+
+    #[test]
+    fn turning_boids_are_milling() {
+        let world = world_with(&[(1.0, 0.0, 0.0, 1.0), (0.0, 1.0, -1.0, 0.0), (-1.0, 0.0, 0.0, -1.0), (0.0, -1.0, 1.0, 0.0)]);
+        let metrics = FlockMetrics::compute(&world);
+        assert!(metrics.polarization < 1e-6);
+        assert!((metrics.milling - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn nearest_neighbours_are_found_through_the_boundary() {
+        let mut world = world_with(&[(9.5, 0.0, 1.0, 0.0), (-9.5, 0.0, 1.0, 0.0), (0.0, 0.0, 1.0, 0.0)]);
+        world.parameters.boundary_mode = BoundaryMode::Wrap;
+        assert_eq!(FlockMetrics::compute(&world).min_nearest_distance, 1.0);
+        world.parameters.boundary_mode = BoundaryMode::Reflect;
+        assert_eq!(FlockMetrics::compute(&world).min_nearest_distance, 9.5);
+    }
+}
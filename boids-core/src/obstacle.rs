@@ -0,0 +1,273 @@
+use crate::vector::Vector;
+
+/// Static obstacle the boids have to fly around
+#[derive(Clone, Debug, PartialEq)]
+pub enum Obstacle {
+    Circle { center: Vector, radius: f32 },
+    /// axis aligned box
+    Box { min: Vector, max: Vector },
+    /// convex polygon, vertices in counter-clockwise order
+    Polygon { vertices: Vec<Vector> },
+}
+
+/// First contact of a ray with an obstacle
+#[derive(Copy, Clone, Debug)]
+pub struct Hit {
+    /// distance along the ray, 0 if the origin of the ray is inside the obstacle
+    pub distance: f32,
+    /// outward unit normal of the obstacle at the contact point
+    pub normal: Vector,
+}
+
+impl Obstacle {
+    pub fn circle(x: f32, y: f32, radius: f32) -> Self {
+        Obstacle::Circle {
+            center: Vector { x, y },
+            radius,
+        }
+    }
+
+    pub fn rectangle(x_min: f32, y_min: f32, x_max: f32, y_max: f32) -> Self {
+        Obstacle::Box {
+            min: Vector { x: x_min.min(x_max), y: y_min.min(y_max) },
+            max: Vector { x: x_min.max(x_max), y: y_min.max(y_max) },
+        }
+    }
+
+    /// Creates a convex polygon. The vertices can be given in any orientation
+    /// but the polygon must be convex and have at least 3 vertices
+    pub fn polygon(mut vertices: Vec<Vector>) -> Result<Self, String> {
+        if vertices.len() < 3 {
+            return Err(format!("A polygon needs at least 3 vertices, got {}", vertices.len()));
+        }
+
+        let nb_vertices = vertices.len();
+        let cross = |v: &Vec<Vector>, i: usize| -> f32 {
+            let a = v[i];
+            let b = v[(i + 1) % nb_vertices];
+            let c = v[(i + 2) % nb_vertices];
+            (b.x - a.x) * (c.y - b.y) - (b.y - a.y) * (c.x - b.x)
+        };
+
+        let area: f32 = (0..nb_vertices)
+            .map(|i| {
+                let a = vertices[i];
+                let b = vertices[(i + 1) % nb_vertices];
+                a.x * b.y - b.x * a.y
+            })
+            .sum();
+        if area.abs() <= f32::EPSILON {
+            return Err("A polygon cannot be flat".to_string());
+        }
+        if area < 0.0 {
+            vertices.reverse();
+        }
+        if (0..nb_vertices).any(|i| cross(&vertices, i) < 0.0) {
+            return Err("A polygon must be convex".to_string());
+        }
+        Ok(Obstacle::Polygon { vertices })
+    }
+
+    /// Casts the ray `origin + t * direction` (`direction` being a unit vector) for
+    /// `t` in `[0, max_distance]` against the obstacle inflated by `margin`
+    pub fn ray_hit(&self, origin: &Vector, direction: &Vector, max_distance: f32, margin: f32) -> Option<Hit> {
+        match self {
+            Obstacle::Circle { center, radius } => circle_hit(center, radius + margin, origin, direction, max_distance),
+            Obstacle::Box { min, max } => {
+                let min = Vector { x: min.x - margin, y: min.y - margin };
+                let max = Vector { x: max.x + margin, y: max.y + margin };
+                box_hit(&min, &max, origin, direction, max_distance)
+            }
+            Obstacle::Polygon { vertices } => polygon_hit(vertices, margin, origin, direction, max_distance),
+        }
+    }
+}
+
+fn dot(lhs: &Vector, rhs: &Vector) -> f32 {
+    lhs.x * rhs.x + lhs.y * rhs.y
+}
+
+fn normalized(vector: Vector) -> Vector {
+    let norm = vector.hypot();
+    if norm <= f32::EPSILON {
+        return Vector { x: 1.0, y: 0.0 };
+    }
+    Vector { x: vector.x / norm, y: vector.y / norm }
+}
+
+fn circle_hit(center: &Vector, radius: f32, origin: &Vector, direction: &Vector, max_distance: f32) -> Option<Hit> {
+    let mut offset = *origin;
+    offset.subtract(center);
+
+    let c = offset.norm() - radius * radius;
+    if c <= 0.0 {
+        return Some(Hit { distance: 0.0, normal: normalized(offset) });
+    }
+
+    let b = dot(&offset, direction);
+    let discriminant = b * b - c;
+    if b > 0.0 || discriminant < 0.0 {
+        return None;
+    }
+    let distance = -b - discriminant.sqrt();
+    if distance > max_distance {
+        return None;
+    }
+    offset.add_scaled(direction, distance);
+    Some(Hit { distance, normal: normalized(offset) })
+}
+
+fn box_hit(min: &Vector, max: &Vector, origin: &Vector, direction: &Vector, max_distance: f32) -> Option<Hit> {
+    let inside = origin.x > min.x && origin.x < max.x && origin.y > min.y && origin.y < max.y;
+    if inside {
+        // push out through the closest side
+        let sides = [
+            (origin.x - min.x, Vector { x: -1.0, y: 0.0 }),
+            (max.x - origin.x, Vector { x: 1.0, y: 0.0 }),
+            (origin.y - min.y, Vector { x: 0.0, y: -1.0 }),
+            (max.y - origin.y, Vector { x: 0.0, y: 1.0 }),
+        ];
+        let closest = sides
+            .iter()
+            .min_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal))
+            .map(|s| s.1)
+            .unwrap_or(Vector { x: 1.0, y: 0.0 });
+        return Some(Hit { distance: 0.0, normal: closest });
+    }
+
+    let mut t_enter = 0.0f32;
+    let mut t_exit = max_distance;
+    let mut normal = Vector::new();
+
+    for axis in 0..2 {
+        let (o, d, lo, hi) = if axis == 0 {
+            (origin.x, direction.x, min.x, max.x)
+        } else {
+            (origin.y, direction.y, min.y, max.y)
+        };
+        if d.abs() <= f32::EPSILON {
+            if o < lo || o > hi {
+                return None;
+            }
+            continue;
+        }
+        let (t_lo, t_hi) = ((lo - o) / d, (hi - o) / d);
+        let (t_near, t_far) = if t_lo < t_hi { (t_lo, t_hi) } else { (t_hi, t_lo) };
+        if t_near > t_enter {
+            t_enter = t_near;
+            normal = Vector::new();
+            if axis == 0 {
+                normal.x = -d.signum();
+            } else {
+                normal.y = -d.signum();
+            }
+        }
+        t_exit = t_exit.min(t_far);
+        if t_enter > t_exit {
+            return None;
+        }
+    }
+    Some(Hit { distance: t_enter, normal })
+}
+
+/// Cyrus-Beck clipping of the ray against the half-planes of the polygon sides.
+/// Each side is moved outward by `margin`
+fn polygon_hit(vertices: &[Vector], margin: f32, origin: &Vector, direction: &Vector, max_distance: f32) -> Option<Hit> {
+    let mut t_enter = f32::NEG_INFINITY;
+    let mut t_exit = f32::INFINITY;
+    let mut enter_normal = Vector::new();
+    // side the origin is the closest to when it is inside
+    let mut closest_side = (f32::NEG_INFINITY, Vector::new());
+
+    for i in 0..vertices.len() {
+        let a = &vertices[i];
+        let b = &vertices[(i + 1) % vertices.len()];
+        let normal = normalized(Vector { x: b.y - a.y, y: a.x - b.x });
+
+        let mut from_side = *origin;
+        from_side.subtract(a);
+        // signed distance of the origin to the inflated side, positive outside
+        let distance = dot(&normal, &from_side) - margin;
+        let approach = dot(&normal, direction);
+
+        if distance > closest_side.0 {
+            closest_side = (distance, normal);
+        }
+
+        if approach.abs() <= f32::EPSILON {
+            if distance > 0.0 {
+                return None;
+            }
+            continue;
+        }
+        let t = -distance / approach;
+        if approach < 0.0 {
+            if t > t_enter {
+                t_enter = t;
+                enter_normal = normal;
+            }
+        } else {
+            t_exit = t_exit.min(t);
+        }
+    }
+
+    if closest_side.0 <= 0.0 {
+        return Some(Hit { distance: 0.0, normal: closest_side.1 });
+    }
+    if t_enter > t_exit || t_enter < 0.0 || t_enter > max_distance {
+        return None;
+    }
+    Some(Hit { distance: t_enter, normal: enter_normal })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RIGHT: Vector = Vector { x: 1.0, y: 0.0 };
+
+    fn origin() -> Vector {
+        Vector::new()
+    }
+
+    #[test]
+    fn ray_hits_the_side_facing_it() {
+        let square = vec![
+            Vector { x: 2.0, y: -1.0 },
+            Vector { x: 4.0, y: -1.0 },
+            Vector { x: 4.0, y: 1.0 },
+            Vector { x: 2.0, y: 1.0 },
+        ];
+        let obstacles = [
+            Obstacle::circle(3.0, 0.0, 1.0),
+            Obstacle::rectangle(2.0, -1.0, 4.0, 1.0),
+            Obstacle::polygon(square).unwrap(),
+        ];
+
+        for obstacle in obstacles.iter() {
+            let hit = obstacle.ray_hit(&origin(), &RIGHT, 5.0, 0.5).unwrap();
+            assert!((hit.distance - 1.5).abs() < 1e-5, "{:?}", obstacle);
+            assert!((hit.normal.x + 1.0).abs() < 1e-5, "{:?}", obstacle);
+
+            assert!(obstacle.ray_hit(&origin(), &RIGHT, 1.0, 0.5).is_none());
+            assert!(obstacle.ray_hit(&origin(), &Vector { x: -1.0, y: 0.0 }, 5.0, 0.5).is_none());
+            assert_eq!(obstacle.ray_hit(&Vector { x: 3.0, y: 0.2 }, &RIGHT, 5.0, 0.5).unwrap().distance, 0.0);
+        }
+    }
+
+    #[test]
+    fn polygons_must_be_convex() {
+        let clockwise_triangle = vec![origin(), Vector { x: 0.0, y: 1.0 }, Vector { x: 1.0, y: 0.0 }];
+        assert!(Obstacle::polygon(clockwise_triangle).is_ok());
+
+        let concave = vec![
+            origin(),
+            Vector { x: 2.0, y: 0.0 },
+            Vector { x: 1.0, y: 0.5 },
+            Vector { x: 2.0, y: 2.0 },
+            Vector { x: 0.0, y: 2.0 },
+        ];
+        assert!(Obstacle::polygon(concave).is_err());
+        assert!(Obstacle::polygon(vec![origin(), RIGHT]).is_err());
+    }
+}
@@ -0,0 +1,187 @@
+use crate::vector::Vector;
+
+/// Polyline, or closed loop, of waypoints the boids fly along
+#[derive(Clone, Debug, PartialEq)]
+pub struct WaypointPath {
+    pub waypoints: Vec<Vector>,
+    /// the last waypoint is connected to the first one
+    pub closed: bool,
+    /// a boid closer than this distance to the path and flying along it is not steered
+    pub radius: f32,
+    /// distance ahead along the path of the point a boid steers to
+    pub lookahead: f32,
+}
+
+/// Point of a path the closest to a position
+#[derive(Copy, Clone, Debug)]
+pub struct Projection {
+    /// distance from the first waypoint along the path
+    pub arc_length: f32,
+    pub point: Vector,
+    /// distance between the position and the path
+    pub distance: f32,
+    /// unit direction of the segment containing the point
+    pub direction: Vector,
+}
+
+impl WaypointPath {
+    pub fn new(waypoints: Vec<Vector>, closed: bool, radius: f32, lookahead: f32) -> Result<Self, String> {
+        if waypoints.len() < 2 {
+            return Err(format!("A path needs at least 2 waypoints, got {}", waypoints.len()));
+        }
+        Ok(WaypointPath {
+            waypoints,
+            closed,
+            radius,
+            lookahead,
+        })
+    }
+
+    fn nb_segments(&self) -> usize {
+        if self.closed {
+            self.waypoints.len()
+        } else {
+            self.waypoints.len() - 1
+        }
+    }
+
+    fn segment(&self, i: usize) -> (Vector, Vector) {
+        (self.waypoints[i], self.waypoints[(i + 1) % self.waypoints.len()])
+    }
+
+    pub fn length(&self) -> f32 {
+        (0..self.nb_segments())
+            .map(|i| {
+                let (mut start, end) = self.segment(i);
+                start.subtract(&end);
+                start.hypot()
+            })
+            .sum()
+    }
+
+    pub fn project(&self, position: &Vector) -> Projection {
+        let mut best: Option<Projection> = None;
+        let mut start_length = 0.0;
+        for i in 0..self.nb_segments() {
+            let (start, end) = self.segment(i);
+            let mut direction = end;
+            direction.subtract(&start);
+            let length = direction.hypot();
+            if length <= 1e-6 {
+                continue;
+            }
+            direction.scale(1.0 / length);
+
+            let along = ((position.x - start.x) * direction.x + (position.y - start.y) * direction.y).clamp(0.0, length);
+            let mut point = start;
+            point.add_scaled(&direction, along);
+            let mut offset = *position;
+            offset.subtract(&point);
+            let distance = offset.hypot();
+
+            if best.map_or(true, |b| distance < b.distance) {
+                best = Some(Projection {
+                    arc_length: start_length + along,
+                    point,
+                    distance,
+                    direction,
+                });
+            }
+            start_length += length;
+        }
+        best.unwrap_or(Projection {
+            arc_length: 0.0,
+            point: self.waypoints[0],
+            distance: 0.0,
+            direction: Vector { x: 1.0, y: 0.0 },
+        })
+    }
+
+    /// the point at `arc_length` from the first waypoint, going round a closed
+    /// path and stopping at the ends of an open one
+    pub fn point_at(&self, arc_length: f32) -> Vector {
+        let total = self.length();
+        let mut remaining = if self.closed && total > 0.0 {
+            arc_length.rem_euclid(total)
+        } else {
+            arc_length.clamp(0.0, total)
+        };
+        for i in 0..self.nb_segments() {
+            let (start, end) = self.segment(i);
+            let mut direction = end;
+            direction.subtract(&start);
+            let length = direction.hypot();
+            if remaining <= length && length > 1e-6 {
+                let mut point = start;
+                point.add_scaled(&direction, remaining / length);
+                return point;
+            }
+            remaining -= length;
+        }
+        self.segment(self.nb_segments() - 1).1
+    }
+
+    /// Computes the steering of a boid toward the point `lookahead` ahead of its projection on the path.
+    /// Returns false, and no steering, if the boid is already within the path radius and flying along it
+    pub fn steer(&self, position: &Vector, velocity: &Vector, max_speed: f32, steering: &mut Vector) -> bool {
+        steering.clear();
+        let projection = self.project(position);
+        let along = velocity.x * projection.direction.x + velocity.y * projection.direction.y;
+        if projection.distance <= self.radius && along > 0.0 {
+            return false;
+        }
+
+        *steering = self.point_at(projection.arc_length + self.lookahead);
+        steering.subtract(position);
+        let distance = steering.hypot();
+        if distance <= 1e-6 {
+            return false;
+        }
+        steering.scale(max_speed / distance);
+        steering.subtract(velocity);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square() -> WaypointPath {
+        let waypoints = vec![
+            Vector { x: 0.0, y: 0.0 },
+            Vector { x: 2.0, y: 0.0 },
+            Vector { x: 2.0, y: 2.0 },
+            Vector { x: 0.0, y: 2.0 },
+        ];
+        WaypointPath::new(waypoints, true, 0.2, 0.5).unwrap()
+    }
+
+    #[test]
+    fn projection_and_point_at_agree() {
+        let path = square();
+        assert_eq!(path.length(), 8.0);
+
+        let projection = path.project(&Vector { x: 2.5, y: 1.0 });
+        assert_eq!(projection.arc_length, 3.0);
+        assert_eq!(projection.distance, 0.5);
+        assert_eq!(path.point_at(3.0), projection.point);
+        assert_eq!(path.point_at(9.0), Vector { x: 1.0, y: 0.0 });
+
+        let mut open = square();
+        open.closed = false;
+        assert_eq!(open.point_at(7.0), Vector { x: 0.0, y: 2.0 });
+    }
+
+    #[test]
+    fn boids_are_steered_back_along_the_path() {
+        let path = square();
+        let mut steering = Vector::new();
+
+        assert!(!path.steer(&Vector { x: 1.0, y: 0.1 }, &Vector { x: 1.0, y: 0.0 }, 1.0, &mut steering));
+        assert!(path.steer(&Vector { x: 1.0, y: 0.1 }, &Vector { x: -1.0, y: 0.0 }, 1.0, &mut steering));
+        assert!(steering.x > 0.0);
+        assert!(path.steer(&Vector { x: 1.0, y: -1.0 }, &Vector { x: 1.0, y: 0.0 }, 1.0, &mut steering));
+        assert!(steering.y > 0.0);
+    }
+}
@@ -0,0 +1,156 @@
+use rand::Rng;
+
+use crate::world::{compute_cos_max_angle, Parameters};
+
+// smallest factor of a drawn personality
+const MIN_FACTOR: f32 = 0.01;
+
+/// Distribution of a trait of the boids
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Distribution {
+    Constant(f32),
+    Uniform { min: f32, max: f32 },
+    Normal { mean: f32, std_dev: f32 },
+}
+
+impl Distribution {
+    /// draws a value. A constant does not use the random generator
+    pub fn sample(&self, rng: &mut impl Rng) -> f32 {
+        match *self {
+            Distribution::Constant(value) => value,
+            Distribution::Uniform { min, max } => min + (max - min) * rng.gen::<f32>(),
+            Distribution::Normal { mean, std_dev } => {
+                // Box-Muller transform
+                let u1 = 1.0 - rng.gen::<f32>();
+                let u2 = rng.gen::<f32>();
+                let z = (-2.0 * u1.ln()).sqrt() * (2.0 * std::f32::consts::PI * u2).cos();
+                mean + std_dev * z
+            }
+        }
+    }
+}
+
+/// Traits of a boid, as factors applied to the parameters of its species
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Personality {
+    /// scales the minimal and maximal speeds
+    pub speed: f32,
+    pub visibility: f32,
+    pub separation: f32,
+    pub alignment: f32,
+    pub cohesion: f32,
+    pub dead_angle: f32,
+}
+
+impl Personality {
+    /// the personality of a boid behaving exactly as its species
+    pub fn new() -> Self {
+        Personality {
+            speed: 1.0,
+            visibility: 1.0,
+            separation: 1.0,
+            alignment: 1.0,
+            cohesion: 1.0,
+            dead_angle: 1.0,
+        }
+    }
+
+    /// cosine of the half angle of view of the boid, the dead angle of the species adjusted
+    /// with this personality
+    pub fn cos_max_angle(&self, parameters: &Parameters) -> f32 {
+        if self.dead_angle == 1.0 {
+            parameters.cos_max_angle()
+        } else {
+            compute_cos_max_angle((parameters.dead_angle() * self.dead_angle).min(180.0))
+        }
+    }
+}
+
+/// Distributions the personalities of the boids are drawn from by `World::initialize`.
+/// Draws below 0.01 are replaced by 0.01
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PersonalityDistributions {
+    pub speed: Distribution,
+    pub visibility: Distribution,
+    pub separation: Distribution,
+    pub alignment: Distribution,
+    pub cohesion: Distribution,
+    pub dead_angle: Distribution,
+}
+
+impl PersonalityDistributions {
+    /// every boid behaves as its species
+    pub fn new() -> Self {
+        PersonalityDistributions {
+            speed: Distribution::Constant(1.0),
+            visibility: Distribution::Constant(1.0),
+            separation: Distribution::Constant(1.0),
+            alignment: Distribution::Constant(1.0),
+            cohesion: Distribution::Constant(1.0),
+            dead_angle: Distribution::Constant(1.0),
+        }
+    }
+
+    /// every factor is drawn from a normal distribution of mean 1 and standard deviation `std_dev`
+    pub fn normal(std_dev: f32) -> Self {
+        let distribution = Distribution::Normal { mean: 1.0, std_dev };
+        PersonalityDistributions {
+            speed: distribution,
+            visibility: distribution,
+            separation: distribution,
+            alignment: distribution,
+            cohesion: distribution,
+            dead_angle: distribution,
+        }
+    }
+
+    pub fn sample(&self, rng: &mut impl Rng) -> Personality {
+        Personality {
+            speed: self.speed.sample(rng).max(MIN_FACTOR),
+            visibility: self.visibility.sample(rng).max(MIN_FACTOR),
+            separation: self.separation.sample(rng).max(MIN_FACTOR),
+            alignment: self.alignment.sample(rng).max(MIN_FACTOR),
+            cohesion: self.cohesion.sample(rng).max(MIN_FACTOR),
+            dead_angle: self.dead_angle.sample(rng).max(MIN_FACTOR),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn samples_follow_the_distributions() {
+        let mut rng = StdRng::seed_from_u64(1);
+        assert_eq!(Distribution::Constant(2.0).sample(&mut rng), 2.0);
+
+        let uniform = Distribution::Uniform { min: 1.0, max: 3.0 };
+        assert!((0..100).map(|_| uniform.sample(&mut rng)).all(|v| (1.0..=3.0).contains(&v)));
+
+        let normal = Distribution::Normal { mean: 5.0, std_dev: 0.5 };
+        let mean = (0..10000).map(|_| normal.sample(&mut rng)).sum::<f32>() / 10000.0;
+        assert!((mean - 5.0).abs() < 0.05, "{}", mean);
+    }
+
+    #[test]
+    fn personality_scales_the_dead_angle() {
+        let parameters = Parameters::new();
+        assert_eq!(Personality::new().cos_max_angle(&parameters), parameters.cos_max_angle());
+
+        let personality = Personality { dead_angle: 0.5, ..Personality::new() };
+        assert_eq!(personality.cos_max_angle(&parameters), compute_cos_max_angle(0.5 * parameters.dead_angle()));
+    }
+
+    #[test]
+    fn drawn_factors_are_positive() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let distributions = PersonalityDistributions::normal(2.0);
+        for _ in 0..100 {
+            let personality = distributions.sample(&mut rng);
+            assert!(personality.speed >= MIN_FACTOR && personality.dead_angle >= MIN_FACTOR);
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use crate::boid::Boid;
+use crate::vector::Vector;
+use crate::world::World;
+
+const DEFAULT_PREDATOR_MIN_SPEED: f32 = 4.0;
+const DEFAULT_PREDATOR_MAX_SPEED: f32 = 14.0;
+const DEFAULT_PREDATOR_VISION_RADIUS: f32 = 3.0;
+const DEFAULT_PREDATOR_CATCH_RADIUS: f32 = 0.15;
+const DEFAULT_PREDATOR_PURSUIT_FACTOR: f32 = 0.1;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum HuntStrategy {
+    /// chase the closest visible boid
+    Nearest,
+    /// chase the visible boid having the fewest neighbours
+    MostIsolated,
+}
+
+pub struct PredatorParameters {
+    pub min_speed: f32,
+    pub max_speed: f32,
+    pub vision_radius: f32,
+    /// a boid closer than this distance to the predator chasing it is caught
+    pub catch_radius: f32,
+    /// how fast the predator velocity turns toward its target
+    pub pursuit_factor: f32,
+    pub strategy: HuntStrategy,
+}
+
+impl PredatorParameters {
+    pub fn new() -> Self {
+        PredatorParameters {
+            min_speed: DEFAULT_PREDATOR_MIN_SPEED,
+            max_speed: DEFAULT_PREDATOR_MAX_SPEED,
+            vision_radius: DEFAULT_PREDATOR_VISION_RADIUS,
+            catch_radius: DEFAULT_PREDATOR_CATCH_RADIUS,
+            pursuit_factor: DEFAULT_PREDATOR_PURSUIT_FACTOR,
+            strategy: HuntStrategy::Nearest,
+        }
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct Predator {
+    pub body: Boid,
+    /// index of the boid being chased
+    pub target: Option<usize>,
+}
+
+impl Predator {
+    pub fn new(position: Vector, velocity: Vector) -> Self {
+        let mut body = Boid::new();
+        body.position = position;
+        body.velocity = velocity;
+        body.update_speed();
+        Predator { body, target: None }
+    }
+
+    /// Selects the boid to chase among the boids of `world` within the vision radius, through the
+    /// boundary in `Wrap` mode. The neighbours of a boid are counted, with the `MostIsolated`
+    /// strategy, within the visibility radius of the first species
+    pub fn choose_target(&mut self, world: &World, buffer: &mut Vec<usize>) {
+        let parameters = &world.predator_parameters;
+        let boids = &world.current;
+        let position = self.body.position;
+        world.boids_within(&position, parameters.vision_radius, buffer);
+        self.target = match parameters.strategy {
+            HuntStrategy::Nearest => buffer
+                .iter()
+                .map(|&i| (distance(world, &position, &boids[i].position), i))
+                .fold(None, |best: Option<(f32, usize)>, (d, i)| match best {
+                    Some((best_distance, _)) if best_distance <= d => best,
+                    _ => Some((d, i)),
+                })
+                .map(|(_, i)| i),
+            HuntStrategy::MostIsolated => {
+                let visible = buffer.clone();
+                let mut best: Option<(usize, f32, usize)> = None;
+                for i in visible {
+                    world.boids_within(&boids[i].position, world.parameters.visibility_radius, buffer);
+                    let nb_neighbours = buffer.len();
+                    let d = distance(world, &position, &boids[i].position);
+                    let better = match best {
+                        None => true,
+                        Some((n, best_distance, _)) => nb_neighbours < n || (nb_neighbours == n && d < best_distance),
+                    };
+                    if better {
+                        best = Some((nb_neighbours, d, i));
+                    }
+                }
+                best.map(|b| b.2)
+            }
+        };
+    }
+
+    /// turns toward the target of `world`, if any, through the boundary in `Wrap` mode, and moves
+    pub fn pursue(&mut self, world: &World, dt: f32) {
+        let parameters = &world.predator_parameters;
+        if let Some(target) = self.target {
+            let mut desired = world.parameters.boundary_mode.closest_image(
+                &self.body.position,
+                &world.current[target].position,
+                world.playfield_size,
+            );
+            desired.subtract(&self.body.position);
+            let norm = desired.hypot();
+            if norm > 1e-6 {
+                desired.scale(parameters.max_speed / norm);
+                desired.subtract(&self.body.velocity);
+                self.body.velocity.add_scaled(&desired, parameters.pursuit_factor);
+            }
+        }
+        self.body.update_speed();
+        self.clamp_speed(parameters);
+        self.body.update_position(dt);
+    }
+
+    fn clamp_speed(&mut self, parameters: &PredatorParameters) {
+        let speed = self.body.speed();
+        if speed <= 1e-6 {
+            self.body.velocity = Vector { x: parameters.min_speed, y: 0.0 };
+        } else if speed > parameters.max_speed {
+            self.body.velocity.scale(parameters.max_speed / speed);
+        } else if speed < parameters.min_speed {
+            self.body.velocity.scale(parameters.min_speed / speed);
+        }
+        self.body.update_speed();
+    }
+}
+
+/// distance between two positions of `world`, through the boundary in `Wrap` mode
+pub(crate) fn distance(world: &World, lhs: &Vector, rhs: &Vector) -> f32 {
+    let mut separation = *lhs;
+    separation.subtract(&world.parameters.boundary_mode.closest_image(lhs, rhs, world.playfield_size));
+    separation.hypot()
+}
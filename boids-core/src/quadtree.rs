@@ -0,0 +1,173 @@
+use crate::boid::Boid;
+use crate::spatial_index::{
+    box_distance2, copy_positions, distance2, is_within, pruning_distance2, KNearest, SpatialIndex,
+};
+use crate::vector::Vector;
+
+const MAX_ITEMS_PER_LEAF: usize = 8;
+const MAX_DEPTH: usize = 16;
+
+const NO_CHILDREN: usize = 0;
+
+struct Node {
+    min: Vector,
+    max: Vector,
+    // indices in QuadTree::indices of the boids in this node
+    start: usize,
+    end: usize,
+    // index of the first of the four children, NO_CHILDREN for a leaf
+    first_child: usize,
+}
+
+/// Region quadtree. Each node covers a square split in four quadrants
+/// until a leaf contains at most `MAX_ITEMS_PER_LEAF` boids.
+pub struct QuadTree {
+    nodes: Vec<Node>,
+    indices: Vec<usize>,
+    positions: Vec<Vector>,
+}
+
+impl QuadTree {
+    pub fn new() -> Self {
+        QuadTree {
+            nodes: vec![],
+            indices: vec![],
+            positions: vec![],
+        }
+    }
+
+    fn split(&mut self, node: usize, depth: usize) {
+        let (start, end) = (self.nodes[node].start, self.nodes[node].end);
+        if end - start <= MAX_ITEMS_PER_LEAF || depth >= MAX_DEPTH {
+            return;
+        }
+
+        let min = self.nodes[node].min;
+        let max = self.nodes[node].max;
+        let center = Vector {
+            x: (min.x + max.x) * 0.5,
+            y: (min.y + max.y) * 0.5,
+        };
+
+        let positions = &self.positions;
+        let quadrant = |i: &usize| -> usize {
+            let p = &positions[*i];
+            (if p.x < center.x { 0 } else { 1 }) + (if p.y < center.y { 0 } else { 2 })
+        };
+        self.indices[start..end].sort_by_key(quadrant);
+
+        let first_child = self.nodes.len();
+        let mut child_start = start;
+        for q in 0..4 {
+            let child_end = child_start
+                + self.indices[child_start..end]
+                    .iter()
+                    .take_while(|i| quadrant(i) == q)
+                    .count();
+            let (x_min, x_max) = if q & 1 == 0 { (min.x, center.x) } else { (center.x, max.x) };
+            let (y_min, y_max) = if q & 2 == 0 { (min.y, center.y) } else { (center.y, max.y) };
+            self.nodes.push(Node {
+                min: Vector { x: x_min, y: y_min },
+                max: Vector { x: x_max, y: y_max },
+                start: child_start,
+                end: child_end,
+                first_child: NO_CHILDREN,
+            });
+            child_start = child_end;
+        }
+        self.nodes[node].first_child = first_child;
+
+        for child in first_child..first_child + 4 {
+            self.split(child, depth + 1);
+        }
+    }
+
+    fn collect_within(&self, node: usize, position: &Vector, radius: f32, result: &mut Vec<usize>) {
+        let n = &self.nodes[node];
+        if n.start == n.end || box_distance2(position, &n.min, &n.max) > pruning_distance2(radius) {
+            return;
+        }
+        if n.first_child == NO_CHILDREN {
+            for i in &self.indices[n.start..n.end] {
+                if is_within(position, &self.positions[*i], radius) {
+                    result.push(*i);
+                }
+            }
+        } else {
+            for child in n.first_child..n.first_child + 4 {
+                self.collect_within(child, position, radius, result);
+            }
+        }
+    }
+
+    fn collect_nearest(&self, node: usize, position: &Vector, nearest: &mut KNearest) {
+        let n = &self.nodes[node];
+        if n.start == n.end || box_distance2(position, &n.min, &n.max) > nearest.worst_distance2() {
+            return;
+        }
+        if n.first_child == NO_CHILDREN {
+            for i in &self.indices[n.start..n.end] {
+                nearest.offer(distance2(position, &self.positions[*i]), *i);
+            }
+        } else {
+            let mut children: Vec<(f32, usize)> = (n.first_child..n.first_child + 4)
+                .map(|c| (box_distance2(position, &self.nodes[c].min, &self.nodes[c].max), c))
+                .collect();
+            children.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+            for (_, child) in children {
+                self.collect_nearest(child, position, nearest);
+            }
+        }
+    }
+}
+
+impl SpatialIndex for QuadTree {
+    fn rebuild(&mut self, boids: &[Boid], _radius: f32) {
+        copy_positions(boids, &mut self.positions);
+        self.indices.clear();
+        self.indices.extend(0..boids.len());
+        self.nodes.clear();
+
+        if boids.is_empty() {
+            return;
+        }
+
+        let mut min = boids[0].position;
+        let mut max = boids[0].position;
+        for boid in boids.iter() {
+            min.x = min.x.min(boid.position.x);
+            min.y = min.y.min(boid.position.y);
+            max.x = max.x.max(boid.position.x);
+            max.y = max.y.max(boid.position.y);
+        }
+        // make the root square so that the quadrants stay square
+        let size = (max.x - min.x).max(max.y - min.y);
+        max.x = (min.x + size).max(max.x);
+        max.y = (min.y + size).max(max.y);
+
+        self.nodes.push(Node {
+            min,
+            max,
+            start: 0,
+            end: boids.len(),
+            first_child: NO_CHILDREN,
+        });
+        self.split(0, 0);
+    }
+
+    fn within_radius(&self, position: &Vector, radius: f32, result: &mut Vec<usize>) {
+        result.clear();
+        if !self.nodes.is_empty() {
+            self.collect_within(0, position, radius, result);
+        }
+        result.sort_unstable();
+    }
+
+    fn nearest(&self, position: &Vector, k: usize, result: &mut Vec<usize>) {
+        let mut nearest = KNearest::new(k);
+        if !self.nodes.is_empty() && k > 0 {
+            self.collect_nearest(0, position, &mut nearest);
+        }
+        nearest.fill(result);
+    }
+}
@@ -0,0 +1,218 @@
+//! Recording of a run : the initial state of the world and everything that happened to it.
+//!
+//! A recording file starts with `boids-recording 1`, followed by a snapshot of the initial
+//! state (see `snapshot`), a line `events` and one event per line :
+//! ```text
+//! step <dt>
+//! parameter <species> <name> <value>...
+//! step_mode sequential | parallel <nb_threads>
+//! ```
+//! `parameter` and `step_mode` lines have the same syntax as in a snapshot : the random
+//! generators of a step depend on the number of threads, so a change of step mode is recorded too. What a snapshot does not save, the
+//! attractors for instance, is not recorded either, so the replay is exact only without it.
+
+use std::fs;
+use std::path::Path;
+
+use crate::snapshot;
+use crate::world::{StepMode, World};
+
+pub const VERSION: u32 = 1;
+const HEADER: &str = "boids-recording";
+const EVENTS: &str = "events";
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event {
+    Step { dt: f32 },
+    Parameter { species: usize, name: String, value: String },
+    StepMode(StepMode),
+}
+
+pub struct Recording {
+    initial_state: String,
+    events: Vec<Event>,
+}
+
+impl Recording {
+    /// Starts recording `world`. The random generator of the world is reset to its seed, and the
+    /// cached speeds of its boids recomputed, so that its state can be restored exactly from the snapshot.
+    /// Fails if the world cannot be saved in a snapshot
+    pub fn start(world: &mut World) -> Result<Self, String> {
+        let seed = world.seed();
+        world.set_seed(seed);
+        for boid in world.current.iter_mut() {
+            boid.update_speed();
+        }
+        Ok(Recording {
+            initial_state: snapshot::to_string(world)?,
+            events: vec![],
+        })
+    }
+
+    pub fn events(&self) -> &[Event] {
+        &self.events
+    }
+
+    pub fn record_step(&mut self, dt: f32) {
+        self.events.push(Event::Step { dt });
+    }
+
+    /// Changes a parameter of a species, written as in a snapshot, and records the change
+    pub fn set_parameter(&mut self, world: &mut World, species: usize, name: &str, value: &str) -> Result<(), String> {
+        apply_parameter(world, species, name, value)?;
+        self.events.push(Event::Parameter {
+            species,
+            name: name.to_string(),
+            value: value.to_string(),
+        });
+        Ok(())
+    }
+
+    /// Changes the step mode of the world and records the change
+    pub fn set_step_mode(&mut self, world: &mut World, step_mode: StepMode) {
+        world.step_mode = step_mode;
+        self.events.push(Event::StepMode(step_mode));
+    }
+
+    /// Replays the recording from its initial state, calling `on_step` after each step.
+    /// Returns the final state of the world
+    pub fn replay(&self, mut on_step: impl FnMut(&World)) -> Result<World, String> {
+        let mut world = snapshot::parse(&self.initial_state)?;
+        for event in self.events.iter() {
+            match event {
+                Event::Step { dt } => {
+                    world.compute(*dt);
+                    on_step(&world);
+                }
+                Event::Parameter { species, name, value } => apply_parameter(&mut world, *species, name, value)?,
+                Event::StepMode(step_mode) => world.step_mode = *step_mode,
+            }
+        }
+        Ok(world)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), String> {
+        let path = path.as_ref();
+        fs::write(path, self.to_string()).map_err(|e| format!("Cannot write recording '{}' : {}", path.display(), e))
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path).map_err(|e| format!("Cannot read recording '{}' : {}", path.display(), e))?;
+        Recording::parse(&content).map_err(|e| format!("Invalid recording '{}' : {}", path.display(), e))
+    }
+
+    pub fn parse(content: &str) -> Result<Self, String> {
+        let mut lines = content.lines().enumerate();
+        match lines.next() {
+            Some((_, line)) if line.trim() == format!("{} {}", HEADER, VERSION) => {}
+            _ => return Err(format!("line 1 : expected '{} {}'", HEADER, VERSION)),
+        }
+
+        let mut initial_state = String::new();
+        let mut in_events = false;
+        let mut events = vec![];
+        for (line_index, line) in lines {
+            if !in_events {
+                if line.trim() == EVENTS {
+                    in_events = true;
+                } else {
+                    initial_state.push_str(line);
+                    initial_state.push('\n');
+                }
+                continue;
+            }
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            events.push(parse_event(line).map_err(|e| format!("line {} : {}", line_index + 1, e))?);
+        }
+
+        // checked now rather than at the replay
+        snapshot::parse(&initial_state).map_err(|e| format!("initial state, {}", e))?;
+        Ok(Recording { initial_state, events })
+    }
+}
+
+impl std::fmt::Display for Recording {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{} {}", HEADER, VERSION)?;
+        write!(f, "{}", self.initial_state)?;
+        writeln!(f, "{}", EVENTS)?;
+        for event in self.events.iter() {
+            match event {
+                Event::Step { dt } => writeln!(f, "step {}", dt)?,
+                Event::Parameter { species, name, value } => writeln!(f, "parameter {} {} {}", species, name, value)?,
+                Event::StepMode(step_mode) => writeln!(f, "step_mode {}", snapshot::step_mode_value(step_mode))?,
+            }
+        }
+        Ok(())
+    }
+}
+
+fn apply_parameter(world: &mut World, species: usize, name: &str, value: &str) -> Result<(), String> {
+    if species >= world.nb_species() {
+        return Err(format!("unknown species {}", species));
+    }
+    snapshot::set_parameter(world.species_parameters_mut(species), name, value)
+}
+
+fn parse_event(line: &str) -> Result<Event, String> {
+    let mut tokens = line.split_whitespace();
+    match tokens.next().unwrap_or("") {
+        "step" => {
+            let dt = tokens.next().ok_or_else(|| "missing dt".to_string())?;
+            let dt = dt.parse().map_err(|_| format!("'{}' is not a number", dt))?;
+            Ok(Event::Step { dt })
+        }
+        "parameter" => {
+            let species = tokens.next().ok_or_else(|| "missing species".to_string())?;
+            let species = species.parse().map_err(|_| format!("'{}' is not an integer", species))?;
+            let name = tokens.next().ok_or_else(|| "missing parameter name".to_string())?.to_string();
+            let value = tokens.collect::<Vec<&str>>().join(" ");
+            Ok(Event::Parameter { species, name, value })
+        }
+        "step_mode" => Ok(Event::StepMode(snapshot::parse_step_mode(&tokens.collect::<Vec<&str>>())?)),
+        other => Err(format!("unknown event '{}'", other)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replay_is_exact() {
+        let mut world = World::new(300, 3.0).with_seed(8);
+        world.step_mode = StepMode::Parallel { nb_threads: 3 };
+        world.initialize();
+        world.add_predator();
+        world.compute(0.02);
+
+        let mut recording = Recording::start(&mut world).unwrap();
+        assert_eq!(world.seed(), 8);
+        for i in 0..20 {
+            if i == 10 {
+                recording.set_parameter(&mut world, 0, "cohesion_factor", "0.3").unwrap();
+                recording.set_parameter(&mut world, 0, "neighbour_mode", "topological 5").unwrap();
+            }
+            if i == 15 {
+                recording.set_step_mode(&mut world, StepMode::Sequential);
+            }
+            world.compute(0.02);
+            recording.record_step(0.02);
+        }
+        assert!(recording.set_parameter(&mut world, 0, "unknown", "1").is_err());
+
+        let recording = Recording::parse(&recording.to_string()).unwrap();
+        let mut nb_steps = 0;
+        let replayed = recording.replay(|_| nb_steps += 1).unwrap();
+        assert_eq!(nb_steps, 20);
+        assert_eq!(replayed.catches(), world.catches());
+        for (r, w) in replayed.current.iter().zip(world.current.iter()) {
+            assert_eq!(r.position, w.position);
+            assert_eq!(r.velocity, w.velocity);
+        }
+    }
+}
@@ -0,0 +1,307 @@
+use crate::steering::{Neighbour, SteeringContext, SteeringRule};
+use crate::vector::Vector;
+
+/// the rules of a new world, in the order their contributions are added
+pub fn default_rules() -> Vec<Box<dyn SteeringRule>> {
+    vec![
+        Box::new(Separation),
+        Box::new(Alignment),
+        Box::new(Cohesion),
+        Box::new(Damping),
+        Box::new(Avoidance),
+        Box::new(Flee),
+        Box::new(Attraction),
+        Box::new(PathFollowing),
+    ]
+}
+
+/// moves away from the neighbours in the safe space
+pub struct Separation;
+
+/// turns toward the mean heading of the neighbours
+pub struct Alignment;
+
+/// moves toward the center of the neighbours
+pub struct Cohesion;
+
+/// lets go of the current velocity in proportion to the alignment factor. With `Alignment`,
+/// replaces this part of the velocity with the mean heading of the neighbours
+pub struct Damping;
+
+/// turns away from the closest obstacle ahead
+pub struct Avoidance;
+
+/// flies away from the visible predators
+pub struct Flee;
+
+/// follows the pull of the attractors and repulsors
+pub struct Attraction;
+
+/// flies along the path of the species, if any
+pub struct PathFollowing;
+
+/// The neighbours of lower index than the reference boid, and the other ones. The flocking rules
+/// add the terms of the reference boid between both, then remove it, as the original model did,
+/// so their results are the same to the last bit
+fn around_reference<'a>(context: &SteeringContext<'a>) -> (&'a [Neighbour], &'a [Neighbour]) {
+    let position = context.neighbours.partition_point(|n| n.index < context.index);
+    context.neighbours.split_at(position)
+}
+
+impl SteeringRule for Separation {
+    fn name(&self) -> &'static str {
+        "Separation"
+    }
+
+    fn steer(&self, context: &SteeringContext, contribution: &mut Vector) -> bool {
+        contribution.clear();
+        if context.neighbours.is_empty() {
+            return false;
+        }
+        let reference = context.reference;
+        let interaction = context.world.interactions.get(reference.species, reference.species);
+        let (before, after) = around_reference(context);
+        for neighbour in before.iter().filter(|n| n.in_safe_space) {
+            contribution.add_scaled(&neighbour.separation, neighbour.interaction.separation);
+        }
+        contribution.add_scaled(&context.jitter, interaction.separation);
+        for neighbour in after.iter().filter(|n| n.in_safe_space) {
+            contribution.add_scaled(&neighbour.separation, neighbour.interaction.separation);
+        }
+        contribution.scale(context.parameters.separation_factor * reference.personality.separation);
+        true
+    }
+}
+
+impl SteeringRule for Alignment {
+    fn name(&self) -> &'static str {
+        "Alignment"
+    }
+
+    fn steer(&self, context: &SteeringContext, contribution: &mut Vector) -> bool {
+        contribution.clear();
+        if context.neighbours.is_empty() {
+            return false;
+        }
+        let reference = context.reference;
+        let interaction = context.world.interactions.get(reference.species, reference.species);
+        let (before, after) = around_reference(context);
+        let mut weight = 0.0;
+        for neighbour in before.iter() {
+            weight += neighbour.interaction.alignment;
+            contribution.add_scaled(&neighbour.velocity, neighbour.interaction.alignment);
+        }
+        weight += interaction.alignment;
+        contribution.add_scaled(&reference.velocity, interaction.alignment);
+        for neighbour in after.iter() {
+            weight += neighbour.interaction.alignment;
+            contribution.add_scaled(&neighbour.velocity, neighbour.interaction.alignment);
+        }
+        //remove myself
+        weight -= interaction.alignment;
+        contribution.add_scaled(&reference.velocity, -interaction.alignment);
+
+        if weight > 0.0 {
+            contribution.scale(1. / weight);
+        } else {
+            // nobody to align with : keep the current velocity
+            *contribution = reference.velocity;
+        }
+        contribution.scale(context.parameters.alignment_factor * reference.personality.alignment);
+        true
+    }
+}
+
+impl SteeringRule for Cohesion {
+    fn name(&self) -> &'static str {
+        "Cohesion"
+    }
+
+    fn steer(&self, context: &SteeringContext, contribution: &mut Vector) -> bool {
+        contribution.clear();
+        if context.neighbours.is_empty() {
+            return false;
+        }
+        let reference = context.reference;
+        let interaction = context.world.interactions.get(reference.species, reference.species);
+        let (before, after) = around_reference(context);
+        let mut weight = 0.0;
+        for neighbour in before.iter() {
+            weight += neighbour.interaction.cohesion;
+            contribution.add_scaled(&neighbour.position, neighbour.interaction.cohesion);
+        }
+        weight += interaction.cohesion;
+        contribution.add_scaled(&reference.position, interaction.cohesion);
+        for neighbour in after.iter() {
+            weight += neighbour.interaction.cohesion;
+            contribution.add_scaled(&neighbour.position, neighbour.interaction.cohesion);
+        }
+        //remove myself
+        weight -= interaction.cohesion;
+        contribution.add_scaled(&reference.position, -interaction.cohesion);
+
+        if weight > 0.0 {
+            contribution.scale(1. / weight);
+            contribution.subtract(&reference.position);
+        } else {
+            contribution.clear();
+        }
+        contribution.scale(context.parameters.cohesion_factor * reference.personality.cohesion);
+        true
+    }
+}
+
+impl SteeringRule for Damping {
+    fn name(&self) -> &'static str {
+        "Damping"
+    }
+
+    fn steer(&self, context: &SteeringContext, contribution: &mut Vector) -> bool {
+        contribution.clear();
+        if context.neighbours.is_empty() {
+            return false;
+        }
+        let reference = context.reference;
+        *contribution = reference.velocity;
+        contribution.scale(-(context.parameters.alignment_factor * reference.personality.alignment));
+        true
+    }
+}
+
+impl SteeringRule for Avoidance {
+    fn name(&self) -> &'static str {
+        "Avoidance"
+    }
+
+    /// Looks ahead along the velocity of the boid for the closest obstacle and,
+    /// if any, turns away from it. The closer the obstacle, the stronger the steering.
+    fn steer(&self, context: &SteeringContext, contribution: &mut Vector) -> bool {
+        contribution.clear();
+        let world = context.world;
+        let reference = context.reference;
+        let parameters = context.parameters;
+        let speed = reference.speed();
+        if world.obstacles.is_empty() || speed <= 1e-6 {
+            return false;
+        }
+
+        let direction = Vector {
+            x: reference.velocity.x / speed,
+            y: reference.velocity.y / speed,
+        };
+        let lookahead = speed * world.parameters.avoidance_lookahead;
+        let margin = parameters.bird_size;
+
+        let hit = world
+            .obstacles
+            .iter()
+            .filter_map(|o| o.ray_hit(&reference.position, &direction, lookahead, margin))
+            .min_by(|a, b| a.distance.partial_cmp(&b.distance).unwrap_or(std::cmp::Ordering::Equal));
+
+        let hit = match hit {
+            Some(hit) => hit,
+            None => return false,
+        };
+
+        // turn sideways, toward the side the obstacle surface is facing
+        let along = hit.normal.x * direction.x + hit.normal.y * direction.y;
+        let mut lateral = hit.normal;
+        lateral.add_scaled(&direction, -along);
+        if lateral.hypot() <= 1e-3 {
+            lateral = Vector { x: -direction.y, y: direction.x };
+        }
+        lateral.scale(1.0 / lateral.hypot());
+
+        *contribution = lateral;
+        contribution.add(&hit.normal);
+        let max_speed = parameters.max_bird_speed * reference.personality.speed;
+        contribution.scale(max_speed * (1.0 - hit.distance / lookahead.max(1e-6)));
+        contribution.scale(parameters.avoidance_factor);
+        true
+    }
+}
+
+impl SteeringRule for Flee {
+    fn name(&self) -> &'static str {
+        "Flee"
+    }
+
+    /// Steers away from the visible predators, stronger for the closest ones
+    fn steer(&self, context: &SteeringContext, contribution: &mut Vector) -> bool {
+        contribution.clear();
+        let world = context.world;
+        let reference = context.reference;
+        let parameters = context.parameters;
+        let flee_radius = parameters.flee_radius;
+        let max_speed = parameters.max_bird_speed * reference.personality.speed;
+        let cos_max_angle = reference.personality.cos_max_angle(parameters);
+        let mut sees_predator = false;
+        for predator in world.predators.iter() {
+            let position = world.parameters.boundary_mode.closest_image(
+                &reference.position,
+                &predator.body.position,
+                world.playfield_size,
+            );
+            let mut separation = reference.position;
+            separation.subtract(&position);
+            let distance = separation.hypot();
+            if distance > flee_radius || distance <= 1e-6 {
+                continue;
+            }
+            let prod = (separation.x * reference.velocity.x + separation.y * reference.velocity.y)
+                / (distance * reference.speed());
+            if prod < cos_max_angle {
+                continue;
+            }
+            sees_predator = true;
+            contribution.add_scaled(&separation, max_speed * (1.0 - distance / flee_radius) / distance);
+        }
+        contribution.scale(parameters.flee_factor);
+        sees_predator
+    }
+}
+
+impl SteeringRule for Attraction {
+    fn name(&self) -> &'static str {
+        "Attraction"
+    }
+
+    /// Sums the pulls of the attractors and repulsors within reach
+    fn steer(&self, context: &SteeringContext, contribution: &mut Vector) -> bool {
+        contribution.clear();
+        let world = context.world;
+        let reference = context.reference;
+        let mut attracted = false;
+        for attractor in world.attractors.iter() {
+            let center = world.parameters.boundary_mode.closest_image(
+                &reference.position,
+                &attractor.position,
+                world.playfield_size,
+            );
+            attracted |= attractor.pull(&reference.position, &center, contribution);
+        }
+        attracted
+    }
+}
+
+impl SteeringRule for PathFollowing {
+    fn name(&self) -> &'static str {
+        "Path following"
+    }
+
+    fn steer(&self, context: &SteeringContext, contribution: &mut Vector) -> bool {
+        contribution.clear();
+        let reference = context.reference;
+        let parameters = context.parameters;
+        let path = match parameters.path.and_then(|p| context.world.paths.get(p)) {
+            Some(path) => path,
+            None => return false,
+        };
+        let max_speed = parameters.max_bird_speed * reference.personality.speed;
+        if !path.steer(&reference.position, &reference.velocity, max_speed, contribution) {
+            return false;
+        }
+        contribution.scale(parameters.path_factor);
+        true
+    }
+}
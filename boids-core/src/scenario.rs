@@ -0,0 +1,249 @@
+use std::fs;
+use std::path::Path;
+
+use crate::flow::{FlowField, FlowGrid};
+use crate::obstacle::Obstacle;
+use crate::path::WaypointPath;
+use crate::vector::Vector;
+use crate::world::World;
+
+/// Elements of a world that are not boids, described in a text file.
+///
+/// One element per line, values separated by spaces, `#` starts a comment :
+/// ```text
+/// circle <x> <y> <radius>
+/// box <x_min> <y_min> <x_max> <y_max>
+/// polygon <x1> <y1> <x2> <y2> <x3> <y3> ...
+/// path <radius> <lookahead> <x1> <y1> <x2> <y2> ...
+/// loop <radius> <lookahead> <x1> <y1> <x2> <y2> ...
+/// wind <vx> <vy>
+/// vortex <x> <y> <strength> <radius>
+/// grid <file>
+/// ```
+/// `loop` is a closed path and `grid` loads a flow grid, see `FlowGrid::load`, from a file
+/// relative to the directory of the scenario. A file containing spaces or `#` is written between
+/// double quotes, with a backslash before the quotes and backslashes of its name : see `quote`
+pub struct Scenario {
+    pub obstacles: Vec<Obstacle>,
+    pub paths: Vec<WaypointPath>,
+    pub flow_fields: Vec<FlowField>,
+}
+
+impl Scenario {
+    pub fn new() -> Self {
+        Scenario {
+            obstacles: vec![],
+            paths: vec![],
+            flow_fields: vec![],
+        }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path).map_err(|e| format!("Cannot read scenario '{}' : {}", path.display(), e))?;
+        Scenario::parse_in(&content, directory_of(path)).map_err(|e| format!("Invalid scenario '{}' : {}", path.display(), e))
+    }
+
+    /// Parses a scenario, the relative files being relative to the working directory
+    pub fn parse(content: &str) -> Result<Self, String> {
+        Scenario::parse_in(content, Path::new("."))
+    }
+
+    /// Parses a scenario, the relative files being relative to `directory`
+    pub fn parse_in(content: &str, directory: &Path) -> Result<Self, String> {
+        let mut scenario = Scenario::new();
+        for (line_index, line) in content.lines().enumerate() {
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+            scenario
+                .parse_line(line, directory)
+                .map_err(|e| format!("line {} : {}", line_index + 1, e))?;
+        }
+        Ok(scenario)
+    }
+
+    /// adds the elements of this scenario to the world
+    pub fn apply(&self, world: &mut World) {
+        world.obstacles.extend(self.obstacles.iter().cloned());
+        world.paths.extend(self.paths.iter().cloned());
+        world.flow_fields.extend(self.flow_fields.iter().cloned());
+    }
+
+    pub(crate) fn parse_line(&mut self, line: &str, directory: &Path) -> Result<(), String> {
+        let mut tokens = line.split_whitespace();
+        let keyword = tokens.next().unwrap_or("");
+        if keyword == "grid" {
+            let file = unquote(line[keyword.len()..].trim())?;
+            if file.is_empty() {
+                return Err("'grid' expects a file".to_string());
+            }
+            self.flow_fields.push(FlowField::Grid(FlowGrid::load(directory.join(file))?));
+            return Ok(());
+        }
+        let values = tokens
+            .map(|t| t.parse::<f32>().map_err(|_| format!("'{}' is not a number", t)))
+            .collect::<Result<Vec<f32>, String>>()?;
+
+        match keyword {
+            "circle" => {
+                check_count(keyword, &values, 3)?;
+                if values[2] <= 0.0 {
+                    return Err("the radius of a circle must be positive".to_string());
+                }
+                self.obstacles.push(Obstacle::circle(values[0], values[1], values[2]));
+            }
+            "box" => {
+                check_count(keyword, &values, 4)?;
+                self.obstacles.push(Obstacle::rectangle(values[0], values[1], values[2], values[3]));
+            }
+            "polygon" => {
+                if values.len() % 2 != 0 {
+                    return Err("a polygon needs pairs of coordinates".to_string());
+                }
+                let vertices = values.chunks(2).map(|c| Vector { x: c[0], y: c[1] }).collect();
+                self.obstacles.push(Obstacle::polygon(vertices)?);
+            }
+            "path" | "loop" => {
+                if values.len() < 2 || values.len() % 2 != 0 {
+                    return Err(format!("'{}' expects a radius, a lookahead and pairs of coordinates", keyword));
+                }
+                let waypoints = values[2..].chunks(2).map(|c| Vector { x: c[0], y: c[1] }).collect();
+                self.paths.push(WaypointPath::new(waypoints, keyword == "loop", values[0], values[1])?);
+            }
+            "wind" => {
+                check_count(keyword, &values, 2)?;
+                self.flow_fields.push(FlowField::uniform(values[0], values[1]));
+            }
+            "vortex" => {
+                check_count(keyword, &values, 4)?;
+                self.flow_fields.push(FlowField::vortex(values[0], values[1], values[2], values[3]));
+            }
+            _ => return Err(format!("unknown element '{}'", keyword)),
+        }
+        Ok(())
+    }
+}
+
+/// directory of a file, `.` for a file of the working directory
+pub(crate) fn directory_of(path: &Path) -> &Path {
+    match path.parent() {
+        Some(directory) if !directory.as_os_str().is_empty() => directory,
+        _ => Path::new("."),
+    }
+}
+
+/// Writes a path between double quotes, with a backslash before its quotes and backslashes
+pub(crate) fn quote(path: &Path) -> String {
+    format!("\"{}\"", path.to_string_lossy().replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Reads a path written by `quote`, or as it is if it does not start with a quote
+fn unquote(value: &str) -> Result<String, String> {
+    let quoted = match value.strip_prefix('"') {
+        Some(quoted) => quoted,
+        None => return Ok(value.to_string()),
+    };
+    let mut result = String::new();
+    let mut chars = quoted.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => result.push(chars.next().ok_or_else(|| "unterminated quote".to_string())?),
+            '"' if chars.as_str().trim().is_empty() => return Ok(result),
+            '"' => return Err(format!("unexpected '{}' after the quote", chars.as_str().trim())),
+            c => result.push(c),
+        }
+    }
+    Err("unterminated quote".to_string())
+}
+
+/// The line without its comment, which starts at the first `#` out of quotes
+pub(crate) fn strip_comment(line: &str) -> &str {
+    let mut in_quotes = false;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_quotes => escaped = true,
+            '"' => in_quotes = !in_quotes,
+            '#' if !in_quotes => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+fn check_count(keyword: &str, values: &[f32], expected: usize) -> Result<(), String> {
+    if values.len() != expected {
+        return Err(format!("'{}' expects {} values, got {}", keyword, expected, values.len()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_obstacles() {
+        let scenario = Scenario::parse(
+            "# obstacles\n\
+             circle 1 2 0.5\n\
+             \n\
+             box -1 -1 1 1 # a square\n\
+             polygon 0 0 1 0 0 1\n\
+             loop 0.5 1 0 0 4 0 4 4\n\
+             wind 1 0\n",
+        )
+        .unwrap();
+
+        assert_eq!(scenario.obstacles.len(), 3);
+        assert_eq!(scenario.paths.len(), 1);
+        assert!(scenario.paths[0].closed);
+        assert_eq!(scenario.flow_fields, vec![FlowField::uniform(1.0, 0.0)]);
+        assert_eq!(scenario.obstacles[0], Obstacle::circle(1.0, 2.0, 0.5));
+        assert_eq!(scenario.obstacles[1], Obstacle::rectangle(-1.0, -1.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn grid_is_loaded_from_its_file() {
+        let path = std::env::temp_dir().join("boids_scenario_grid.csv");
+        fs::write(&path, "0,0,1\n1,0, 1,0\n").unwrap();
+        let scenario = Scenario::parse(&format!("grid {}\n", path.display())).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        match &scenario.flow_fields[0] {
+            FlowField::Grid(grid) => assert_eq!(grid.source, Some(path)),
+            other => panic!("{:?}", other),
+        }
+        assert!(Scenario::parse("grid\n").is_err());
+    }
+
+    #[test]
+    fn grid_files_are_relative_to_the_scenario() {
+        let directory = std::env::temp_dir().join("boids scenario #1");
+        fs::create_dir_all(&directory).unwrap();
+        let grid = directory.join("wind \"#2\".csv");
+        fs::write(&grid, "0,0,1\n1,0, 1,0\n").unwrap();
+        let path = directory.join("scenario.txt");
+        fs::write(&path, format!("grid {} # the wind\n", quote(Path::new("wind \"#2\".csv")))).unwrap();
+
+        let scenario = Scenario::load(&path);
+        fs::remove_dir_all(&directory).unwrap();
+        match &scenario.unwrap().flow_fields[0] {
+            FlowField::Grid(loaded) => assert_eq!(loaded.source.as_ref().unwrap().file_name(), grid.file_name()),
+            other => panic!("{:?}", other),
+        }
+        assert_eq!(unquote("\"a\\\\b\" ").unwrap(), "a\\b");
+        assert!(Scenario::parse("grid \"a.csv\n").is_err());
+    }
+
+    #[test]
+    fn reports_the_invalid_line() {
+        let error = Scenario::parse("circle 0 0 1\nbox 0 0 1\n").err().unwrap();
+        assert!(error.starts_with("line 2"), "{}", error);
+        assert!(Scenario::parse("triangle 0 0").is_err());
+        assert!(Scenario::parse("circle 0 zero 1").is_err());
+    }
+}
@@ -0,0 +1,619 @@
+//! Text snapshot of a whole `World`, to stop a run and resume it later. The random generator is not
+//! saved, so the resumed run starts from the same state but does not follow the saved one exactly.
+//!
+//! One item per line, values separated by spaces, `#` starts a comment. The first line
+//! gives the format version, the other lines can come in any order except that `species`
+//! must come before the `parameter` and `interaction` lines.
+//! ```text
+//! boids-snapshot 1
+//! playfield_size <size>
+//! seed <seed>
+//! step_mode sequential | parallel <nb_threads>
+//! catches <count>
+//! species <nb_species>
+//! parameter <species> <name> <value>...
+//! interaction <species> <other> <separation> <alignment> <cohesion>
+//! personalities <name> constant <value> | uniform <min> <max> | normal <mean> <std_dev>
+//! predator_parameters <min_speed> <max_speed> <vision_radius> <catch_radius> <pursuit_factor> nearest | most_isolated
+//! boid <species> <x> <y> <vx> <vy> <speed> <visibility> <separation> <alignment> <cohesion> <dead_angle>
+//! predator <x> <y> <vx> <vy>
+//! ```
+//! The parameter names are the ones of the fields of `Parameters`, `path` is `none` or an index,
+//! `neighbour_mode` is `metric` or `topological <k>`, `physics` is `direct` or `reynolds` and
+//! `boundary_mode` is `wrap`, `reflect`, `circle` or `steer <strength> <margin_x> <margin_y> <nudge>`.
+//! The last six values of a boid are its personality.
+//!
+//! Obstacles, paths and flow fields use the lines of a `Scenario` : a grid flow field is saved as the
+//! file it was loaded from, relative to the snapshot if it is in its directory, and cannot be saved
+//! if it was not loaded from a file.
+//! Attractors, custom steering rules and the state of the random generator are not saved :
+//! a loaded world uses the default rules and is reseeded with its seed.
+
+use std::fs;
+use std::path::Path;
+
+use crate::boid::Boid;
+use crate::boundary::BoundaryMode;
+use crate::flow::FlowField;
+use crate::obstacle::Obstacle;
+use crate::personality::Distribution;
+use crate::predator::{HuntStrategy, Predator};
+use crate::scenario::{self, Scenario};
+use crate::species::Interaction;
+use crate::vector::Vector;
+use crate::world::{NeighbourMode, Parameters, PhysicsModel, StepMode, World};
+
+pub const VERSION: u32 = 1;
+const HEADER: &str = "boids-snapshot";
+
+pub fn save(world: &World, path: impl AsRef<Path>) -> Result<(), String> {
+    let path = path.as_ref();
+    fs::write(path, to_string_in(world, scenario::directory_of(path))?).map_err(|e| format!("Cannot write snapshot '{}' : {}", path.display(), e))
+}
+
+pub fn load(path: impl AsRef<Path>) -> Result<World, String> {
+    let path = path.as_ref();
+    let content = fs::read_to_string(path).map_err(|e| format!("Cannot read snapshot '{}' : {}", path.display(), e))?;
+    parse_in(&content, scenario::directory_of(path)).map_err(|e| format!("Invalid snapshot '{}' : {}", path.display(), e))
+}
+
+/// Writes the snapshot of a world, with the absolute paths of its files
+pub fn to_string(world: &World) -> Result<String, String> {
+    to_string_in(world, Path::new(""))
+}
+
+/// Writes the snapshot of a world to be saved in `directory`, with the paths of the files
+/// in this directory relative to it
+pub fn to_string_in(world: &World, directory: &Path) -> Result<String, String> {
+    let directory = fs::canonicalize(directory).ok();
+    let mut lines = vec![
+        format!("{} {}", HEADER, VERSION),
+        format!("playfield_size {}", world.playfield_size),
+        format!("seed {}", world.seed()),
+        format!("step_mode {}", step_mode_value(&world.step_mode)),
+        format!("catches {}", world.catches()),
+        format!("species {}", world.nb_species()),
+    ];
+
+    for species in 0..world.nb_species() {
+        write_parameters(species, world.species_parameters(species), &mut lines);
+    }
+    for species in 0..world.nb_species() {
+        for other in 0..world.nb_species() {
+            let i = world.interactions.get(species, other);
+            lines.push(format!("interaction {} {} {} {} {}", species, other, i.separation, i.alignment, i.cohesion));
+        }
+    }
+
+    let personalities = &world.personalities;
+    for (name, distribution) in [
+        ("speed", personalities.speed),
+        ("visibility", personalities.visibility),
+        ("separation", personalities.separation),
+        ("alignment", personalities.alignment),
+        ("cohesion", personalities.cohesion),
+        ("dead_angle", personalities.dead_angle),
+    ]
+    .iter()
+    {
+        let distribution = match distribution {
+            Distribution::Constant(value) => format!("constant {}", value),
+            Distribution::Uniform { min, max } => format!("uniform {} {}", min, max),
+            Distribution::Normal { mean, std_dev } => format!("normal {} {}", mean, std_dev),
+        };
+        lines.push(format!("personalities {} {}", name, distribution));
+    }
+
+    let p = &world.predator_parameters;
+    let strategy = match p.strategy {
+        HuntStrategy::Nearest => "nearest",
+        HuntStrategy::MostIsolated => "most_isolated",
+    };
+    lines.push(format!(
+        "predator_parameters {} {} {} {} {} {}",
+        p.min_speed, p.max_speed, p.vision_radius, p.catch_radius, p.pursuit_factor, strategy
+    ));
+
+    for boid in world.current.iter() {
+        let p = &boid.personality;
+        lines.push(format!(
+            "boid {} {} {} {} {} {} {} {} {} {} {}",
+            boid.species,
+            boid.position.x,
+            boid.position.y,
+            boid.velocity.x,
+            boid.velocity.y,
+            p.speed,
+            p.visibility,
+            p.separation,
+            p.alignment,
+            p.cohesion,
+            p.dead_angle
+        ));
+    }
+    for predator in world.predators.iter() {
+        let body = &predator.body;
+        lines.push(format!(
+            "predator {} {} {} {}",
+            body.position.x, body.position.y, body.velocity.x, body.velocity.y
+        ));
+    }
+
+    for obstacle in world.obstacles.iter() {
+        lines.push(match obstacle {
+            Obstacle::Circle { center, radius } => format!("circle {} {} {}", center.x, center.y, radius),
+            Obstacle::Box { min, max } => format!("box {} {} {} {}", min.x, min.y, max.x, max.y),
+            Obstacle::Polygon { vertices } => format!("polygon {}", coordinates(vertices)),
+        });
+    }
+    for path in world.paths.iter() {
+        let keyword = if path.closed { "loop" } else { "path" };
+        lines.push(format!("{} {} {} {}", keyword, path.radius, path.lookahead, coordinates(&path.waypoints)));
+    }
+    for field in world.flow_fields.iter() {
+        match field {
+            FlowField::Uniform { velocity } => lines.push(format!("wind {} {}", velocity.x, velocity.y)),
+            FlowField::Vortex { center, strength, radius } => {
+                lines.push(format!("vortex {} {} {} {}", center.x, center.y, strength, radius))
+            }
+            FlowField::Grid(grid) => match &grid.source {
+                Some(path) => {
+                    let relative = directory.as_ref().and_then(|d| path.strip_prefix(d).ok());
+                    lines.push(format!("grid {}", scenario::quote(relative.unwrap_or(path))));
+                }
+                None => return Err("a flow grid not loaded from a file cannot be saved".to_string()),
+            },
+        }
+    }
+
+    let mut result = lines.join("\n");
+    result.push('\n');
+    Ok(result)
+}
+
+/// Parses a snapshot, the relative files being relative to the working directory
+pub fn parse(content: &str) -> Result<World, String> {
+    parse_in(content, Path::new("."))
+}
+
+/// Parses a snapshot, the relative files being relative to `directory`
+pub fn parse_in(content: &str, directory: &Path) -> Result<World, String> {
+    let mut lines = content
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, scenario::strip_comment(line).trim()))
+        .filter(|(_, line)| !line.is_empty());
+
+    match lines.next() {
+        Some((_, line)) if line == format!("{} {}", HEADER, VERSION) => {}
+        Some((line_number, line)) => {
+            return Err(format!("line {} : expected '{} {}', got '{}'", line_number, HEADER, VERSION, line));
+        }
+        None => return Err("empty snapshot".to_string()),
+    }
+
+    let mut world = World::new(0, 1.0);
+    let mut seed = world.seed();
+    let mut scenario = Scenario::new();
+    for (line_number, line) in lines {
+        parse_line(line, directory, &mut world, &mut seed, &mut scenario).map_err(|e| format!("line {} : {}", line_number, e))?;
+    }
+
+    let mut world = world.with_seed(seed);
+    let nb_boids = world.current.len();
+    world.next.resize(nb_boids, Boid::new());
+    scenario.apply(&mut world);
+    Ok(world)
+}
+
+fn parse_line(line: &str, directory: &Path, world: &mut World, seed: &mut u64, scenario: &mut Scenario) -> Result<(), String> {
+    let mut tokens = line.split_whitespace();
+    let keyword = tokens.next().unwrap_or("");
+    let values: Vec<&str> = tokens.collect();
+
+    match keyword {
+        "playfield_size" => world.playfield_size = number(&values, 0)?,
+        "seed" => *seed = integer(&values, 0)?,
+        "step_mode" => world.step_mode = parse_step_mode(&values)?,
+        "catches" => world.set_catches(integer(&values, 0)?),
+        "species" => {
+            let nb_species: usize = integer(&values, 0)?;
+            if nb_species == 0 {
+                return Err("there must be at least one species".to_string());
+            }
+            while world.nb_species() < nb_species {
+                world.add_species(Parameters::new());
+            }
+        }
+        "parameter" => {
+            let species: usize = integer(&values, 0)?;
+            if species >= world.nb_species() {
+                return Err(format!("unknown species {}", species));
+            }
+            parse_parameter(word(&values, 1)?, &values[2..], world.species_parameters_mut(species))?;
+        }
+        "interaction" => {
+            let species: usize = integer(&values, 0)?;
+            let other: usize = integer(&values, 1)?;
+            if species >= world.nb_species() || other >= world.nb_species() {
+                return Err("unknown species".to_string());
+            }
+            let interaction = Interaction {
+                separation: number(&values, 2)?,
+                alignment: number(&values, 3)?,
+                cohesion: number(&values, 4)?,
+            };
+            world.interactions.set(species, other, interaction);
+        }
+        "personalities" => {
+            let distribution = match word(&values, 1)? {
+                "constant" => Distribution::Constant(number(&values, 2)?),
+                "uniform" => Distribution::Uniform { min: number(&values, 2)?, max: number(&values, 3)? },
+                "normal" => Distribution::Normal { mean: number(&values, 2)?, std_dev: number(&values, 3)? },
+                other => return Err(format!("unknown distribution '{}'", other)),
+            };
+            let personalities = &mut world.personalities;
+            match word(&values, 0)? {
+                "speed" => personalities.speed = distribution,
+                "visibility" => personalities.visibility = distribution,
+                "separation" => personalities.separation = distribution,
+                "alignment" => personalities.alignment = distribution,
+                "cohesion" => personalities.cohesion = distribution,
+                "dead_angle" => personalities.dead_angle = distribution,
+                other => return Err(format!("unknown personality trait '{}'", other)),
+            }
+        }
+        "predator_parameters" => {
+            let p = &mut world.predator_parameters;
+            p.min_speed = number(&values, 0)?;
+            p.max_speed = number(&values, 1)?;
+            p.vision_radius = number(&values, 2)?;
+            p.catch_radius = number(&values, 3)?;
+            p.pursuit_factor = number(&values, 4)?;
+            p.strategy = match word(&values, 5)? {
+                "nearest" => HuntStrategy::Nearest,
+                "most_isolated" => HuntStrategy::MostIsolated,
+                other => return Err(format!("unknown hunt strategy '{}'", other)),
+            };
+        }
+        "boid" => {
+            let mut boid = Boid::new();
+            boid.species = integer(&values, 0)?;
+            if boid.species >= world.nb_species() {
+                return Err(format!("unknown species {}", boid.species));
+            }
+            boid.position = Vector { x: number(&values, 1)?, y: number(&values, 2)? };
+            boid.velocity = Vector { x: number(&values, 3)?, y: number(&values, 4)? };
+            boid.update_speed();
+            let p = &mut boid.personality;
+            p.speed = number(&values, 5)?;
+            p.visibility = number(&values, 6)?;
+            p.separation = number(&values, 7)?;
+            p.alignment = number(&values, 8)?;
+            p.cohesion = number(&values, 9)?;
+            p.dead_angle = number(&values, 10)?;
+            world.current.push(boid);
+        }
+        "predator" => {
+            let position = Vector { x: number(&values, 0)?, y: number(&values, 1)? };
+            let velocity = Vector { x: number(&values, 2)?, y: number(&values, 3)? };
+            world.predators.push(Predator::new(position, velocity));
+        }
+        _ => scenario.parse_line(line, directory)?,
+    }
+    Ok(())
+}
+
+fn write_parameters(species: usize, p: &Parameters, lines: &mut Vec<String>) {
+    let mut line = |name: &str, value: String| lines.push(format!("parameter {} {} {}", species, name, value));
+    line("bird_size", p.bird_size.to_string());
+    line("min_bird_speed", p.min_bird_speed.to_string());
+    line("max_bird_speed", p.max_bird_speed.to_string());
+    line("visibility_radius", p.visibility_radius.to_string());
+    line("safe_space_ratio", p.safe_space_ratio.to_string());
+    line("dead_angle", p.dead_angle().to_string());
+    line("separation_factor", p.separation_factor.to_string());
+    line("cohesion_factor", p.cohesion_factor.to_string());
+    line("alignment_factor", p.alignment_factor.to_string());
+    line("avoidance_factor", p.avoidance_factor.to_string());
+    line("avoidance_lookahead", p.avoidance_lookahead.to_string());
+    line("flee_factor", p.flee_factor.to_string());
+    line("flee_radius", p.flee_radius.to_string());
+    line("path", p.path.map_or("none".to_string(), |i| i.to_string()));
+    line("path_factor", p.path_factor.to_string());
+    line("neighbour_mode", neighbour_mode_value(&p.neighbour_mode));
+    line("physics", physics_value(&p.physics).to_string());
+    line("mass", p.mass.to_string());
+    line("max_force", p.max_force.to_string());
+    line("max_turn_rate", p.max_turn_rate.to_string());
+    line("boundary_mode", boundary_mode_value(&p.boundary_mode));
+}
+
+/// value of the `step_mode` line, as written in a snapshot
+pub fn step_mode_value(step_mode: &StepMode) -> String {
+    match step_mode {
+        StepMode::Sequential => "sequential".to_string(),
+        StepMode::Parallel { nb_threads } => format!("parallel {}", nb_threads),
+    }
+}
+
+/// Parses the values of a `step_mode` line
+pub fn parse_step_mode(values: &[&str]) -> Result<StepMode, String> {
+    match word(values, 0)? {
+        "sequential" => Ok(StepMode::Sequential),
+        "parallel" => Ok(StepMode::Parallel { nb_threads: integer(values, 1)? }),
+        other => Err(format!("unknown step mode '{}'", other)),
+    }
+}
+
+/// value of the `neighbour_mode` parameter, as written in a snapshot
+pub fn neighbour_mode_value(neighbour_mode: &NeighbourMode) -> String {
+    match neighbour_mode {
+        NeighbourMode::Metric => "metric".to_string(),
+        NeighbourMode::Topological { k } => format!("topological {}", k),
+    }
+}
+
+/// value of the `physics` parameter, as written in a snapshot
+pub fn physics_value(physics: &PhysicsModel) -> &'static str {
+    match physics {
+        PhysicsModel::Direct => "direct",
+        PhysicsModel::Reynolds => "reynolds",
+    }
+}
+
+/// value of the `boundary_mode` parameter, as written in a snapshot
+pub fn boundary_mode_value(boundary_mode: &BoundaryMode) -> String {
+    match boundary_mode {
+        BoundaryMode::Wrap => "wrap".to_string(),
+        BoundaryMode::Reflect => "reflect".to_string(),
+        BoundaryMode::Circle => "circle".to_string(),
+        BoundaryMode::Steer { strength, margin_x, margin_y, nudge } => {
+            format!("steer {} {} {} {}", strength, margin_x, margin_y, nudge)
+        }
+    }
+}
+
+/// names of the parameters of a species, as written in a snapshot
+pub const PARAMETER_NAMES: [&str; 21] = [
+    "bird_size",
+    "min_bird_speed",
+    "max_bird_speed",
+    "visibility_radius",
+    "safe_space_ratio",
+    "dead_angle",
+    "separation_factor",
+    "cohesion_factor",
+    "alignment_factor",
+    "avoidance_factor",
+    "avoidance_lookahead",
+    "flee_factor",
+    "flee_radius",
+    "path",
+    "path_factor",
+    "neighbour_mode",
+    "physics",
+    "mass",
+    "max_force",
+    "max_turn_rate",
+    "boundary_mode",
+];
+
+/// Checks the parameters that depend on each other, once all of them are set
+pub fn check_parameters(parameters: &Parameters) -> Result<(), String> {
+    if parameters.min_bird_speed > parameters.max_bird_speed {
+        return Err(format!(
+            "the minimal speed {} is above the maximal speed {}",
+            parameters.min_bird_speed, parameters.max_bird_speed
+        ));
+    }
+    Ok(())
+}
+
+/// Sets the parameter `name` from its value written as in a snapshot, `topological 7` for instance
+pub fn set_parameter(parameters: &mut Parameters, name: &str, value: &str) -> Result<(), String> {
+    let values: Vec<&str> = value.split_whitespace().collect();
+    parse_parameter(name, &values, parameters)
+}
+
+fn parse_parameter(name: &str, values: &[&str], p: &mut Parameters) -> Result<(), String> {
+    match name {
+        "bird_size" => p.bird_size = non_negative(values, 0, "the size")?,
+        "min_bird_speed" => p.min_bird_speed = non_negative(values, 0, "the speed")?,
+        "max_bird_speed" => p.max_bird_speed = non_negative(values, 0, "the speed")?,
+        "visibility_radius" => p.visibility_radius = non_negative(values, 0, "the radius")?,
+        "safe_space_ratio" => p.safe_space_ratio = between(values, 0, 0.0, 1.0, "the safe space ratio")?,
+        "dead_angle" => p.set_dead_angle(between(values, 0, 0.0, 360.0, "the dead angle")?),
+        "separation_factor" => p.separation_factor = number(values, 0)?,
+        "cohesion_factor" => p.cohesion_factor = number(values, 0)?,
+        "alignment_factor" => p.alignment_factor = number(values, 0)?,
+        "avoidance_factor" => p.avoidance_factor = number(values, 0)?,
+        "avoidance_lookahead" => p.avoidance_lookahead = number(values, 0)?,
+        "flee_factor" => p.flee_factor = number(values, 0)?,
+        "flee_radius" => p.flee_radius = non_negative(values, 0, "the radius")?,
+        "path" => {
+            p.path = match word(values, 0)? {
+                "none" => None,
+                _ => Some(integer(values, 0)?),
+            }
+        }
+        "path_factor" => p.path_factor = number(values, 0)?,
+        "neighbour_mode" => {
+            p.neighbour_mode = match word(values, 0)? {
+                "metric" => NeighbourMode::Metric,
+                "topological" => match integer(values, 1)? {
+                    0 => return Err("the topological mode needs at least one neighbour".to_string()),
+                    k => NeighbourMode::Topological { k },
+                },
+                other => return Err(format!("unknown neighbour mode '{}'", other)),
+            }
+        }
+        "physics" => {
+            p.physics = match word(values, 0)? {
+                "direct" => PhysicsModel::Direct,
+                "reynolds" => PhysicsModel::Reynolds,
+                other => return Err(format!("unknown physics model '{}'", other)),
+            }
+        }
+        "mass" => {
+            let mass = number(values, 0)?;
+            if mass <= 0.0 || !mass.is_finite() {
+                return Err("the mass must be positive".to_string());
+            }
+            p.mass = mass;
+        }
+        "max_force" => p.max_force = number(values, 0)?,
+        "max_turn_rate" => p.max_turn_rate = non_negative(values, 0, "the turn rate")?,
+        "boundary_mode" => {
+            p.boundary_mode = match word(values, 0)? {
+                "wrap" => BoundaryMode::Wrap,
+                "reflect" => BoundaryMode::Reflect,
+                "circle" => BoundaryMode::Circle,
+                "steer" => BoundaryMode::Steer {
+                    strength: number(values, 1)?,
+                    margin_x: number(values, 2)?,
+                    margin_y: number(values, 3)?,
+                    nudge: number(values, 4)?,
+                },
+                other => return Err(format!("unknown boundary mode '{}'", other)),
+            }
+        }
+        _ => return Err(format!("unknown parameter '{}'", name)),
+    }
+    Ok(())
+}
+
+fn coordinates(points: &[Vector]) -> String {
+    points
+        .iter()
+        .map(|p| format!("{} {}", p.x, p.y))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+fn word<'a>(values: &[&'a str], index: usize) -> Result<&'a str, String> {
+    values.get(index).copied().ok_or_else(|| "missing value".to_string())
+}
+
+fn number(values: &[&str], index: usize) -> Result<f32, String> {
+    let value = word(values, index)?;
+    value.parse().map_err(|_| format!("'{}' is not a number", value))
+}
+
+fn non_negative(values: &[&str], index: usize, what: &str) -> Result<f32, String> {
+    let value = number(values, index)?;
+    if value < 0.0 || value.is_nan() {
+        return Err(format!("{} must not be negative", what));
+    }
+    Ok(value)
+}
+
+fn between(values: &[&str], index: usize, min: f32, max: f32, what: &str) -> Result<f32, String> {
+    let value = number(values, index)?;
+    if !(min..=max).contains(&value) {
+        return Err(format!("{} must be between {} and {}", what, min, max));
+    }
+    Ok(value)
+}
+
+fn integer<T: std::str::FromStr>(values: &[&str], index: usize) -> Result<T, String> {
+    let value = word(values, index)?;
+    value.parse().map_err(|_| format!("'{}' is not an integer", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flow::FlowGrid;
+    use crate::path::WaypointPath;
+    use crate::personality::PersonalityDistributions;
+
+    #[test]
+    fn saved_world_is_loaded_identically() {
+        let mut world = World::new(50, 5.0).with_seed(3);
+        world.add_species(Parameters::new());
+        world.species_parameters_mut(1).set_dead_angle(35.5);
+        world.species_parameters_mut(1).neighbour_mode = NeighbourMode::Topological { k: 5 };
+        world.parameters.boundary_mode = BoundaryMode::Wrap;
+        world.personalities = PersonalityDistributions::normal(0.2);
+        for (i, boid) in world.current.iter_mut().enumerate() {
+            boid.species = i % 2;
+        }
+        world.initialize();
+        world.add_predator();
+        world.obstacles.push(Obstacle::circle(1.0, 1.0, 0.5));
+        world.paths.push(WaypointPath::new(vec![Vector::new(), Vector { x: 1.0, y: 0.0 }], true, 0.2, 0.5).unwrap());
+        world.flow_fields.push(FlowField::vortex(0.0, 0.0, 1.0, 2.0));
+        world.compute(0.02);
+
+        let content = to_string(&world).unwrap();
+        let loaded = parse(&content).unwrap();
+        assert_eq!(to_string(&loaded).unwrap(), content);
+
+        assert_eq!(loaded.species_parameters(1).dead_angle(), 35.5);
+        assert_eq!(loaded.species_parameters(1).cos_max_angle(), world.species_parameters(1).cos_max_angle());
+        for (l, r) in loaded.current.iter().zip(world.current.iter()) {
+            assert_eq!(l.position, r.position);
+            assert_eq!(l.velocity, r.velocity);
+            assert_eq!(l.personality, r.personality);
+        }
+    }
+
+    #[test]
+    fn grids_are_saved_as_their_file() {
+        let path = std::env::temp_dir().join("boids_snapshot_grid.csv");
+        fs::write(&path, "0,0,1\n1,0, 1,0\n").unwrap();
+        let mut world = World::new(0, 5.0);
+        world.flow_fields.push(FlowField::Grid(FlowGrid::load(&path).unwrap()));
+
+        let content = to_string(&world).unwrap();
+        assert!(content.contains(&format!("grid {}\n", scenario::quote(&fs::canonicalize(&path).unwrap()))));
+        let loaded = parse(&content).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.flow_fields, world.flow_fields);
+
+        let grid = FlowGrid::new(Vector::new(), 1.0, 1, vec![Vector::new()]).unwrap();
+        world.flow_fields = vec![FlowField::Grid(grid)];
+        assert!(to_string(&world).is_err());
+    }
+
+    #[test]
+    fn grids_are_found_from_another_directory() {
+        let directory = std::env::temp_dir().join("boids snapshot #1");
+        fs::create_dir_all(directory.join("flows")).unwrap();
+        let grid = directory.join("flows").join("wind #2.csv");
+        fs::write(&grid, "0,0,1\n1,0, 1,0\n").unwrap();
+        let mut world = World::new(0, 5.0);
+        world.flow_fields.push(FlowField::Grid(FlowGrid::load(&grid).unwrap()));
+        let path = directory.join("world.snapshot");
+
+        // the working directory is not the one of the snapshot
+        save(&world, &path).unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        let loaded = load(&path);
+        fs::remove_dir_all(&directory).unwrap();
+        assert!(content.contains(&format!("grid {}\n", scenario::quote(&Path::new("flows").join("wind #2.csv")))));
+        assert_eq!(loaded.unwrap().flow_fields, world.flow_fields);
+    }
+
+    #[test]
+    fn parameter_names_are_the_written_parameters() {
+        let mut lines = vec![];
+        write_parameters(0, &Parameters::new(), &mut lines);
+        assert_eq!(lines.len(), PARAMETER_NAMES.len());
+        for (line, name) in lines.iter().zip(PARAMETER_NAMES.iter()) {
+            let (written, value) = line["parameter 0 ".len()..].split_once(' ').unwrap();
+            assert_eq!(written, *name);
+            set_parameter(&mut Parameters::new(), name, value).unwrap();
+        }
+    }
+
+    #[test]
+    fn reports_the_invalid_line() {
+        assert!(parse("boids-snapshot 2\n").is_err());
+        let error = parse("boids-snapshot 1\nspecies 1\nparameter 1 bird_size 1\n").err().unwrap();
+        assert!(error.starts_with("line 3"), "{}", error);
+        assert!(parse("boids-snapshot 1\nparameter 0 unknown 1\n").is_err());
+        assert_eq!(set_parameter(&mut Parameters::new(), "mass", "0").unwrap_err(), "the mass must be positive");
+    }
+}
@@ -0,0 +1,209 @@
+use crate::boid::Boid;
+use crate::grid::Grid;
+use crate::kdtree::KdTree;
+use crate::quadtree::QuadTree;
+use crate::vector::Vector;
+
+/// Acceleration structure used to find the neighbours of a boid.
+/// Indices returned by the queries are indices in the slice
+/// given to the last call to `rebuild`.
+pub trait SpatialIndex: Send + Sync {
+    /// rebuild the index from the boids positions. `radius` is
+    /// the typical query radius and can be used as a hint to size the structure.
+    fn rebuild(&mut self, boids: &[Boid], radius: f32);
+
+    /// Fills `result` with the indices, in increasing order, of the boids
+    /// at a distance less or equal to `radius` of `position`
+    fn within_radius(&self, position: &Vector, radius: f32, result: &mut Vec<usize>);
+
+    /// Fills `result` with the indices of the `k` boids closest to `position`,
+    /// sorted by increasing distance (ties are sorted by index)
+    fn nearest(&self, position: &Vector, k: usize, result: &mut Vec<usize>);
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum IndexKind {
+    BruteForce,
+    Grid,
+    QuadTree,
+    KdTree,
+}
+
+impl IndexKind {
+    pub fn create(self) -> Box<dyn SpatialIndex> {
+        match self {
+            IndexKind::BruteForce => Box::new(BruteForce::new()),
+            IndexKind::Grid => Box::new(Grid::new()),
+            IndexKind::QuadTree => Box::new(QuadTree::new()),
+            IndexKind::KdTree => Box::new(KdTree::new()),
+        }
+    }
+}
+
+/// Reference implementation checking every boid
+pub struct BruteForce {
+    positions: Vec<Vector>,
+}
+
+impl BruteForce {
+    pub fn new() -> Self {
+        BruteForce { positions: vec![] }
+    }
+}
+
+impl SpatialIndex for BruteForce {
+    fn rebuild(&mut self, boids: &[Boid], _radius: f32) {
+        copy_positions(boids, &mut self.positions);
+    }
+
+    fn within_radius(&self, position: &Vector, radius: f32, result: &mut Vec<usize>) {
+        result.clear();
+        for (i, other) in self.positions.iter().enumerate() {
+            if is_within(position, other, radius) {
+                result.push(i);
+            }
+        }
+    }
+
+    fn nearest(&self, position: &Vector, k: usize, result: &mut Vec<usize>) {
+        let mut nearest = KNearest::new(k);
+        for (i, other) in self.positions.iter().enumerate() {
+            nearest.offer(distance2(position, other), i);
+        }
+        nearest.fill(result);
+    }
+}
+
+pub(crate) fn copy_positions(boids: &[Boid], positions: &mut Vec<Vector>) {
+    positions.clear();
+    positions.extend(boids.iter().map(|b| b.position));
+}
+
+/// same computation as the one used by `World` to check the visibility
+pub(crate) fn is_within(position: &Vector, other: &Vector, radius: f32) -> bool {
+    let mut separation = *position;
+    separation.subtract(other);
+    separation.hypot() <= radius
+}
+
+pub(crate) fn distance2(position: &Vector, other: &Vector) -> f32 {
+    let mut separation = *position;
+    separation.subtract(other);
+    separation.norm()
+}
+
+/// squared distance between a position and an axis aligned box
+pub(crate) fn box_distance2(position: &Vector, min: &Vector, max: &Vector) -> f32 {
+    let dx = (min.x - position.x).max(0.0).max(position.x - max.x);
+    let dy = (min.y - position.y).max(0.0).max(position.y - max.y);
+    dx * dx + dy * dy
+}
+
+/// squared distance beyond which a box can be skipped during a radius query.
+/// Slightly enlarged since `is_within` uses `hypot` and not the squared distance
+pub(crate) fn pruning_distance2(radius: f32) -> f32 {
+    radius * radius * (1.0 + 1e-5)
+}
+
+/// Keeps the `k` closest candidates offered so far
+pub(crate) struct KNearest {
+    k: usize,
+    // sorted by increasing distance then index
+    items: Vec<(f32, usize)>,
+}
+
+impl KNearest {
+    pub(crate) fn new(k: usize) -> Self {
+        KNearest {
+            k,
+            items: Vec::with_capacity(k + 1),
+        }
+    }
+
+    pub(crate) fn is_full(&self) -> bool {
+        self.items.len() >= self.k
+    }
+
+    /// squared distance a candidate must not exceed to be accepted
+    pub(crate) fn worst_distance2(&self) -> f32 {
+        if self.is_full() {
+            self.items.last().map(|i| i.0).unwrap_or(f32::INFINITY)
+        } else {
+            f32::INFINITY
+        }
+    }
+
+    pub(crate) fn offer(&mut self, distance2: f32, index: usize) {
+        if self.k == 0 || distance2 > self.worst_distance2() {
+            return;
+        }
+        let candidate = (distance2, index);
+        let position = self
+            .items
+            .iter()
+            .position(|i| i.0 > candidate.0 || (i.0 == candidate.0 && i.1 > candidate.1))
+            .unwrap_or(self.items.len());
+        self.items.insert(position, candidate);
+        self.items.truncate(self.k);
+    }
+
+    pub(crate) fn fill(&self, result: &mut Vec<usize>) {
+        result.clear();
+        result.extend(self.items.iter().map(|i| i.1));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use super::*;
+
+    fn random_boids(nb_birds: usize, size: f32) -> Vec<Boid> {
+        let mut rng = rand::thread_rng();
+        let mut boids = vec![Boid::new(); nb_birds];
+        for boid in boids.iter_mut() {
+            boid.position.x = (rng.gen::<f32>() - 0.5) * size;
+            boid.position.y = (rng.gen::<f32>() - 0.5) * size;
+        }
+        // a few duplicated positions to check ties
+        for i in 0..(nb_birds / 20) {
+            boids[i + 1].position = boids[i].position;
+        }
+        boids
+    }
+
+    #[test]
+    fn all_backends_match_brute_force() {
+        let kinds = [IndexKind::Grid, IndexKind::QuadTree, IndexKind::KdTree];
+        let mut rng = rand::thread_rng();
+        let mut expected = Vec::new();
+        let mut actual = Vec::new();
+
+        for &(nb_birds, size, radius) in [(0, 1.0, 0.5), (1, 1.0, 0.5), (300, 2.0, 0.3), (2000, 40.0, 0.6)].iter() {
+            let boids = random_boids(nb_birds, size);
+            let mut reference = BruteForce::new();
+            reference.rebuild(&boids, radius);
+
+            for kind in kinds.iter() {
+                let mut index = kind.create();
+                index.rebuild(&boids, radius);
+
+                for _ in 0..200 {
+                    let position = Vector {
+                        x: (rng.gen::<f32>() - 0.5) * size * 1.2,
+                        y: (rng.gen::<f32>() - 0.5) * size * 1.2,
+                    };
+                    reference.within_radius(&position, radius, &mut expected);
+                    index.within_radius(&position, radius, &mut actual);
+                    assert_eq!(expected, actual, "{:?} radius query", kind);
+
+                    let k = rng.gen_range(0..10);
+                    reference.nearest(&position, k, &mut expected);
+                    index.nearest(&position, k, &mut actual);
+                    assert_eq!(expected, actual, "{:?} nearest query", kind);
+                }
+            }
+        }
+    }
+}
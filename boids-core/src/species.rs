@@ -0,0 +1,96 @@
+/// How much a boid takes a visible neighbour into account in each rule,
+/// depending on the species of both
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Interaction {
+    pub separation: f32,
+    pub alignment: f32,
+    pub cohesion: f32,
+}
+
+impl Interaction {
+    /// full interaction, the one between boids of the same species
+    pub fn same() -> Self {
+        Interaction {
+            separation: 1.0,
+            alignment: 1.0,
+            cohesion: 1.0,
+        }
+    }
+
+    /// other species are only avoided
+    pub fn other() -> Self {
+        Interaction {
+            separation: 1.0,
+            alignment: 0.0,
+            cohesion: 0.0,
+        }
+    }
+}
+
+/// Square matrix of the interactions between species.
+/// `get(a, b)` is how a boid of species `a` reacts to a boid of species `b`.
+pub struct InteractionMatrix {
+    nb_species: usize,
+    interactions: Vec<Interaction>,
+}
+
+impl InteractionMatrix {
+    pub fn new(nb_species: usize) -> Self {
+        let mut matrix = InteractionMatrix {
+            nb_species: 0,
+            interactions: vec![],
+        };
+        matrix.resize(nb_species);
+        matrix
+    }
+
+    pub fn nb_species(&self) -> usize {
+        self.nb_species
+    }
+
+    /// changes the number of species, keeping the existing interactions.
+    /// New entries are `Interaction::same()` on the diagonal and `Interaction::other()` elsewhere
+    pub fn resize(&mut self, nb_species: usize) {
+        let mut interactions = Vec::with_capacity(nb_species * nb_species);
+        for a in 0..nb_species {
+            for b in 0..nb_species {
+                let interaction = if a < self.nb_species && b < self.nb_species {
+                    self.get(a, b)
+                } else if a == b {
+                    Interaction::same()
+                } else {
+                    Interaction::other()
+                };
+                interactions.push(interaction);
+            }
+        }
+        self.nb_species = nb_species;
+        self.interactions = interactions;
+    }
+
+    pub fn get(&self, species: usize, other: usize) -> Interaction {
+        self.interactions[species * self.nb_species + other]
+    }
+
+    pub fn set(&mut self, species: usize, other: usize, interaction: Interaction) {
+        self.interactions[species * self.nb_species + other] = interaction;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resize_keeps_the_existing_interactions() {
+        let mut matrix = InteractionMatrix::new(2);
+        let custom = Interaction { separation: 0.5, alignment: 0.2, cohesion: 0.0 };
+        matrix.set(0, 1, custom);
+        matrix.resize(3);
+
+        assert_eq!(matrix.get(0, 1), custom);
+        assert_eq!(matrix.get(1, 0), Interaction::other());
+        assert_eq!(matrix.get(2, 2), Interaction::same());
+        assert_eq!(matrix.get(2, 0), Interaction::other());
+    }
+}
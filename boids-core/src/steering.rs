@@ -0,0 +1,44 @@
+use crate::boid::Boid;
+use crate::species::Interaction;
+use crate::vector::Vector;
+use crate::world::{Parameters, World};
+
+/// A boid seen by the reference boid
+#[derive(Copy, Clone, Debug)]
+pub struct Neighbour {
+    pub index: usize,
+    /// position of the image of the neighbour the closest to the reference boid
+    pub position: Vector,
+    pub velocity: Vector,
+    /// from the neighbour to the reference boid. Replaced by a random vector
+    /// when both are almost at the same position
+    pub separation: Vector,
+    pub in_safe_space: bool,
+    /// how the reference boid reacts to this neighbour
+    pub interaction: Interaction,
+}
+
+/// Everything a rule can use to steer a boid
+pub struct SteeringContext<'a> {
+    pub world: &'a World,
+    /// index of the reference boid in `world.current`
+    pub index: usize,
+    pub reference: &'a Boid,
+    /// parameters of the species of the reference boid, to be adjusted with its personality
+    pub parameters: &'a Parameters,
+    /// the visible boids, in increasing index order, the reference boid excluded
+    pub neighbours: &'a [Neighbour],
+    /// random separation of the reference boid from itself, zero when it is not
+    /// in its own safe space
+    pub jitter: Vector,
+}
+
+/// A behaviour of the boids. The contributions of the rules of a world are added,
+/// in order, to the velocity of each boid at each step
+pub trait SteeringRule: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Computes the weighted contribution of the rule to the velocity of the reference boid.
+    /// Returns false if the rule does not apply, `contribution` is then ignored
+    fn steer(&self, context: &SteeringContext, contribution: &mut Vector) -> bool;
+}
@@ -0,0 +1,150 @@
+use crate::boid::Boid;
+use crate::world::World;
+
+/// Advances a `World` with a fixed time step whatever the frame rate.
+/// The frame time is accumulated and consumed by steps of `1/tick_rate` seconds.
+/// The remaining fraction of step is used to interpolate the boids between
+/// the last two computed states.
+pub struct Stepper {
+    step: f32,
+    max_substeps: usize,
+    accumulator: f32,
+    previous: Vec<Boid>,
+}
+
+impl Stepper {
+    pub fn new(tick_rate: f32, max_substeps: usize) -> Self {
+        Stepper {
+            step: 1.0 / tick_rate,
+            max_substeps: max_substeps.max(1),
+            accumulator: 0.0,
+            previous: vec![],
+        }
+    }
+
+    pub fn tick_rate(&self) -> f32 {
+        1.0 / self.step
+    }
+
+    pub fn set_tick_rate(&mut self, tick_rate: f32) {
+        self.step = 1.0 / tick_rate;
+    }
+
+    pub fn step(&self) -> f32 {
+        self.step
+    }
+
+    pub fn max_substeps(&self) -> usize {
+        self.max_substeps
+    }
+
+    pub fn set_max_substeps(&mut self, max_substeps: usize) {
+        self.max_substeps = max_substeps.max(1);
+    }
+
+    /// Forgets the previous state and the accumulated time, when the world is replaced
+    pub fn reset(&mut self) {
+        self.accumulator = 0.0;
+        self.previous.clear();
+    }
+
+    /// fraction of step elapsed since the last computed state, in [0,1)
+    pub fn alpha(&self) -> f32 {
+        self.accumulator / self.step
+    }
+
+    /// Computes as many steps as fit in the accumulated time, at most `max_substeps`.
+    /// The time that does not fit in this limit is dropped, so a long frame
+    /// (window drag for instance) slows the simulation down instead of making it explode.
+    /// Returns the number of computed steps.
+    pub fn advance(&mut self, world: &mut World, frame_time: f32) -> usize {
+        self.accumulator += frame_time.max(0.0);
+
+        let mut nb_steps = 0;
+        while self.accumulator >= self.step && nb_steps < self.max_substeps {
+            self.previous.clear();
+            self.previous.extend_from_slice(&world.current);
+            world.compute(self.step);
+            self.accumulator -= self.step;
+            nb_steps += 1;
+        }
+
+        if nb_steps == self.max_substeps && self.accumulator >= self.step {
+            self.accumulator %= self.step;
+        }
+        nb_steps
+    }
+
+    /// Fills `result` with the boids linearly interpolated between the previous
+    /// and the current state of the world according to `alpha`
+    pub fn interpolate(&self, world: &World, result: &mut Vec<Boid>) {
+        result.clear();
+        result.extend_from_slice(&world.current);
+        if self.previous.len() != result.len() {
+            return;
+        }
+
+        let alpha = self.alpha();
+        let max_jump = world.playfield_size;
+        for (boid, previous) in result.iter_mut().zip(self.previous.iter()) {
+            // a boid that wrapped around the playfield is not interpolated
+            let jump_x = (boid.position.x - previous.position.x).abs();
+            let jump_y = (boid.position.y - previous.position.y).abs();
+            if jump_x > max_jump || jump_y > max_jump {
+                continue;
+            }
+
+            let mut position = previous.position;
+            position.scale(1.0 - alpha);
+            position.add_scaled(&boid.position, alpha);
+            boid.position = position;
+
+            let mut velocity = previous.velocity;
+            velocity.scale(1.0 - alpha);
+            velocity.add_scaled(&boid.velocity, alpha);
+            boid.velocity = velocity;
+            boid.update_speed();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn steps_are_limited_and_excess_time_dropped() {
+        let mut world = World::new(10, 5.0).with_seed(1);
+        world.initialize();
+        let mut stepper = Stepper::new(100.0, 4);
+
+        assert_eq!(stepper.advance(&mut world, 0.025), 2);
+        assert!((stepper.alpha() - 0.5).abs() < 1e-3);
+
+        assert_eq!(stepper.advance(&mut world, 1.0), 4);
+        assert!(stepper.alpha() < 1.0);
+    }
+
+    #[test]
+    fn interpolation_is_between_last_two_states() {
+        let mut world = World::new(10, 5.0).with_seed(1);
+        world.initialize();
+        let mut stepper = Stepper::new(100.0, 4);
+
+        stepper.advance(&mut world, 0.015);
+        let mut interpolated = vec![];
+        stepper.interpolate(&world, &mut interpolated);
+
+        for ((boid, current), previous) in interpolated.iter().zip(world.current.iter()).zip(stepper.previous.iter()) {
+            let expected = (previous.position.x + current.position.x) * 0.5;
+            assert!((boid.position.x - expected).abs() < 1e-4);
+        }
+
+        stepper.reset();
+        assert_eq!(stepper.alpha(), 0.0);
+        stepper.interpolate(&world, &mut interpolated);
+        for (boid, current) in interpolated.iter().zip(world.current.iter()) {
+            assert_eq!(boid.position, current.position);
+        }
+    }
+}
@@ -0,0 +1,173 @@
+//! Compact binary trajectories : the position and velocity of every boid at each frame.
+//!
+//! All values are little-endian. The file starts with the magic `BTRJ`, the version and the
+//! number of boids as `u32`, then the species of every boid as `u32`. Each frame is its time
+//! as `f32` followed by `x y vx vy` as `f32` for every boid.
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter, ErrorKind, Read, Write};
+use std::path::Path;
+
+use crate::boid::Boid;
+use crate::vector::Vector;
+
+pub const VERSION: u32 = 1;
+const MAGIC: &[u8; 4] = b"BTRJ";
+
+pub struct TrajectoryWriter<W: Write> {
+    output: W,
+    nb_boids: usize,
+}
+
+impl TrajectoryWriter<BufWriter<File>> {
+    pub fn create(path: impl AsRef<Path>, boids: &[Boid]) -> Result<Self, String> {
+        let path = path.as_ref();
+        let file = File::create(path).map_err(|e| format!("Cannot create trajectory '{}' : {}", path.display(), e))?;
+        TrajectoryWriter::new(BufWriter::new(file), boids)
+    }
+}
+
+impl<W: Write> TrajectoryWriter<W> {
+    /// Writes the header. The number of boids and their species are taken from `boids`
+    pub fn new(mut output: W, boids: &[Boid]) -> Result<Self, String> {
+        let mut header = Vec::with_capacity(12 + 4 * boids.len());
+        header.extend_from_slice(MAGIC);
+        header.extend_from_slice(&VERSION.to_le_bytes());
+        header.extend_from_slice(&(boids.len() as u32).to_le_bytes());
+        for boid in boids.iter() {
+            header.extend_from_slice(&(boid.species as u32).to_le_bytes());
+        }
+        output.write_all(&header).map_err(|e| e.to_string())?;
+        Ok(TrajectoryWriter {
+            output,
+            nb_boids: boids.len(),
+        })
+    }
+
+    pub fn write_frame(&mut self, time: f32, boids: &[Boid]) -> Result<(), String> {
+        if boids.len() != self.nb_boids {
+            return Err(format!("expected {} boids, got {}", self.nb_boids, boids.len()));
+        }
+        let mut frame = Vec::with_capacity(4 + 16 * boids.len());
+        frame.extend_from_slice(&time.to_le_bytes());
+        for boid in boids.iter() {
+            for value in [boid.position.x, boid.position.y, boid.velocity.x, boid.velocity.y] {
+                frame.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+        self.output.write_all(&frame).map_err(|e| e.to_string())
+    }
+
+    pub fn flush(&mut self) -> Result<(), String> {
+        self.output.flush().map_err(|e| e.to_string())
+    }
+}
+
+/// State of the boids at one frame
+#[derive(Clone, Debug, PartialEq)]
+pub struct Frame {
+    pub time: f32,
+    pub positions: Vec<Vector>,
+    pub velocities: Vec<Vector>,
+}
+
+pub struct TrajectoryReader<R: Read> {
+    input: R,
+    species: Vec<usize>,
+}
+
+impl TrajectoryReader<BufReader<File>> {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let file = File::open(path).map_err(|e| format!("Cannot read trajectory '{}' : {}", path.display(), e))?;
+        TrajectoryReader::new(BufReader::new(file)).map_err(|e| format!("Invalid trajectory '{}' : {}", path.display(), e))
+    }
+}
+
+impl<R: Read> TrajectoryReader<R> {
+    /// Reads the header
+    pub fn new(mut input: R) -> Result<Self, String> {
+        let mut magic = [0u8; 4];
+        input.read_exact(&mut magic).map_err(|e| e.to_string())?;
+        if &magic != MAGIC {
+            return Err("not a trajectory".to_string());
+        }
+        let version = read_u32(&mut input)?;
+        if version != VERSION {
+            return Err(format!("unsupported version {}", version));
+        }
+        let nb_boids = read_u32(&mut input)? as usize;
+        let species = (0..nb_boids)
+            .map(|_| read_u32(&mut input).map(|s| s as usize))
+            .collect::<Result<Vec<usize>, String>>()?;
+        Ok(TrajectoryReader { input, species })
+    }
+
+    pub fn nb_boids(&self) -> usize {
+        self.species.len()
+    }
+
+    pub fn species(&self) -> &[usize] {
+        &self.species
+    }
+
+    /// the next frame, `None` at the end of the trajectory
+    pub fn read_frame(&mut self) -> Result<Option<Frame>, String> {
+        let mut buffer = vec![0u8; 4 + 16 * self.species.len()];
+        match self.input.read_exact(&mut buffer) {
+            Ok(()) => {}
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e.to_string()),
+        }
+        let values = buffer
+            .chunks_exact(4)
+            .map(|c| f32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+            .collect::<Vec<f32>>();
+        let boids = values[1..].chunks_exact(4);
+        Ok(Some(Frame {
+            time: values[0],
+            positions: boids.clone().map(|b| Vector { x: b[0], y: b[1] }).collect(),
+            velocities: boids.map(|b| Vector { x: b[2], y: b[3] }).collect(),
+        }))
+    }
+}
+
+fn read_u32(input: &mut impl Read) -> Result<u32, String> {
+    let mut bytes = [0u8; 4];
+    input.read_exact(&mut bytes).map_err(|e| e.to_string())?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::World;
+
+    #[test]
+    fn frames_are_read_back() {
+        let mut world = World::new(10, 2.0).with_seed(3);
+        world.add_species(world.parameters.clone());
+        world.initialize();
+        world.current[4].species = 1;
+
+        let mut writer = TrajectoryWriter::new(vec![], &world.current).unwrap();
+        writer.write_frame(0.0, &world.current).unwrap();
+        world.compute(0.1);
+        writer.write_frame(0.1, &world.current).unwrap();
+        assert!(writer.write_frame(0.2, &world.current[1..]).is_err());
+        let bytes = writer.output;
+        assert_eq!(bytes.len(), 12 + 4 * 10 + 2 * (4 + 16 * 10));
+
+        let mut reader = TrajectoryReader::new(&bytes[..]).unwrap();
+        assert_eq!(reader.nb_boids(), 10);
+        assert_eq!(reader.species()[4], 1);
+        assert_eq!(reader.read_frame().unwrap().unwrap().time, 0.0);
+        let frame = reader.read_frame().unwrap().unwrap();
+        assert_eq!(frame.time, 0.1);
+        assert_eq!(frame.positions[7], world.current[7].position);
+        assert_eq!(frame.velocities[7], world.current[7].velocity);
+        assert_eq!(reader.read_frame().unwrap(), None);
+
+        assert!(TrajectoryReader::new(&b"BOID"[..]).is_err());
+    }
+}
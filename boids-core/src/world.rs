@@ -0,0 +1,873 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::attractor::Attractor;
+use crate::boid::Boid;
+use crate::boundary::BoundaryMode;
+use crate::flow::FlowField;
+use crate::obstacle::Obstacle;
+use crate::path::WaypointPath;
+use crate::personality::PersonalityDistributions;
+use crate::predator::{self, Predator, PredatorParameters};
+use crate::species::InteractionMatrix;
+use crate::spatial_index::{IndexKind, SpatialIndex};
+use crate::rules::default_rules;
+use crate::steering::{Neighbour, SteeringContext, SteeringRule};
+use crate::vector::Vector;
+
+const SAFE_SPACE_RATIO: f32 = 0.8;
+const DEFAULT_VISIBILITY_FACTOR: f32 = 3.0;
+
+// in degree
+const DEAD_ANGLE: f32 = 20.0;
+const DEFAULT_SEPARATION_FACTOR: f32 = 6.0;
+const DEFAULT_COHESION_FACTOR: f32 = 4.0;
+const DEFAULT_ALIGNMENT_FACTOR: f32 = 10.;
+
+const RANDOM_FACTOR: f32 = 0.0;
+const DEFAULT_BIRD_SIZE: f32 = 0.2;
+const DEFAULT_BIRD_MIN_SPEED: f32 = 5.0;
+const DEFAULT_BIRD_MAX_SPEED: f32 = 16.0;
+const DEFAULT_AVOIDANCE_FACTOR: f32 = 0.5;
+// in seconds of flight at the current speed
+const DEFAULT_AVOIDANCE_LOOKAHEAD: f32 = 0.2;
+const DEFAULT_FLEE_FACTOR: f32 = 0.5;
+const DEFAULT_FLEE_RADIUS: f32 = 1.5;
+const DEFAULT_PATH_FACTOR: f32 = 0.1;
+const DEFAULT_MASS: f32 = 1.0;
+// in degrees per second, no limit by default
+const DEFAULT_MAX_TURN_RATE: f32 = f32::INFINITY;
+// bank angle, in degrees, at the maximal turn rate
+const MAX_BANK_ANGLE: f32 = 60.0;
+// turn rate, in degrees per second, of the maximal bank angle when the turn rate is not limited
+const FULL_BANK_TURN_RATE: f32 = 540.0;
+const DEFAULT_MAX_FORCE: f32 = 30.0;
+// number of neighbours tracked by starlings
+const DEFAULT_TOPOLOGICAL_K: usize = 7;
+
+const NOT_VISIBLE: u8 = 0;
+const VISIBLE: u8 = 1;
+const IN_SAFE_SPACE: u8 = 2;
+
+#[derive(Clone)]
+pub struct Parameters {
+    pub bird_size: f32,
+    pub min_bird_speed: f32,
+    pub max_bird_speed: f32,
+    pub visibility_radius: f32,
+    pub safe_space_ratio: f32,
+    dead_angle: f32,
+    cos_max_angle: f32,
+    pub separation_factor: f32,
+    pub cohesion_factor: f32,
+    pub alignment_factor: f32,
+    pub avoidance_factor: f32,
+    pub avoidance_lookahead: f32,
+    pub flee_factor: f32,
+    /// distance at which a boid sees a predator
+    pub flee_radius: f32,
+    /// index in `World::paths` of the path followed by the boids, if any
+    pub path: Option<usize>,
+    pub path_factor: f32,
+    pub neighbour_mode: NeighbourMode,
+    pub physics: PhysicsModel,
+    pub mass: f32,
+    /// maximal steering force with the Reynolds physics model
+    pub max_force: f32,
+    /// maximal angular velocity, in degrees per second. Infinite for no limit
+    pub max_turn_rate: f32,
+    pub boundary_mode: BoundaryMode,
+}
+
+
+pub fn compute_cos_max_angle(dead_angle: f32) -> f32 {
+    (std::f32::consts::PI * (1. - dead_angle / 180.)).cos()
+}
+
+impl Parameters {
+    pub fn new() -> Self {
+        Parameters {
+            bird_size: DEFAULT_BIRD_SIZE,
+            visibility_radius: DEFAULT_BIRD_SIZE * DEFAULT_VISIBILITY_FACTOR,
+            safe_space_ratio: SAFE_SPACE_RATIO,
+            dead_angle: DEAD_ANGLE,
+            cos_max_angle: compute_cos_max_angle(DEAD_ANGLE),
+            cohesion_factor: DEFAULT_COHESION_FACTOR * 0.01,
+            separation_factor: DEFAULT_SEPARATION_FACTOR * 0.01,
+            alignment_factor: DEFAULT_ALIGNMENT_FACTOR * 0.01,
+            min_bird_speed: DEFAULT_BIRD_MIN_SPEED,
+            max_bird_speed: DEFAULT_BIRD_MAX_SPEED,
+            avoidance_factor: DEFAULT_AVOIDANCE_FACTOR,
+            avoidance_lookahead: DEFAULT_AVOIDANCE_LOOKAHEAD,
+            flee_factor: DEFAULT_FLEE_FACTOR,
+            flee_radius: DEFAULT_FLEE_RADIUS,
+            path: None,
+            path_factor: DEFAULT_PATH_FACTOR,
+            neighbour_mode: NeighbourMode::Metric,
+            physics: PhysicsModel::Direct,
+            mass: DEFAULT_MASS,
+            max_force: DEFAULT_MAX_FORCE,
+            max_turn_rate: DEFAULT_MAX_TURN_RATE,
+            boundary_mode: BoundaryMode::steer(),
+        }
+    }
+
+    pub fn dead_angle(&self) -> f32 {
+        self.dead_angle
+    }
+
+    pub fn cos_max_angle(&self) -> f32 {
+        self.cos_max_angle
+    }
+
+    pub fn set_dead_angle(&mut self, dead_angle: f32) {
+        self.dead_angle = dead_angle;
+        self.cos_max_angle = compute_cos_max_angle(dead_angle);
+    }
+}
+
+/// How the contributions of the steering rules change the velocity of a boid
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PhysicsModel {
+    /// the contributions are added to the velocity
+    Direct,
+    /// the velocity plus the contributions is a desired velocity. The boid is steered toward
+    /// it by a force limited to `max_force`, divided by `mass` and integrated as an acceleration
+    Reynolds,
+}
+
+impl PhysicsModel {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PhysicsModel::Direct => "Direct",
+            PhysicsModel::Reynolds => "Reynolds",
+        }
+    }
+}
+
+/// How the neighbours of a boid are chosen. In both modes, the boids in the dead angle are not seen
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum NeighbourMode {
+    /// the boids within the visibility radius
+    Metric,
+    /// the `k` nearest boids, whatever their distance
+    Topological { k: usize },
+}
+
+impl NeighbourMode {
+    pub fn topological() -> Self {
+        NeighbourMode::Topological { k: DEFAULT_TOPOLOGICAL_K }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum StepMode {
+    Sequential,
+    Parallel { nb_threads: usize },
+}
+
+impl StepMode {
+    /// parallel mode using as many threads as available cores
+    pub fn parallel() -> Self {
+        let nb_threads = std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1);
+        StepMode::Parallel { nb_threads }
+    }
+}
+
+pub struct World {
+    pub playfield_size: f32,
+    pub step_mode: StepMode,
+    /// parameters of the species 0, also used for the settings that
+    /// do not depend on the species (boundary mode, avoidance look ahead)
+    pub parameters: Parameters,
+    /// parameters of the species 1, 2...
+    pub other_species: Vec<Parameters>,
+    pub interactions: InteractionMatrix,
+    /// distributions the personalities of the boids are drawn from by `initialize`
+    pub personalities: PersonalityDistributions,
+    pub current: Vec<Boid>,
+    pub next: Vec<Boid>,
+    pub obstacles: Vec<Obstacle>,
+    pub attractors: Vec<Attractor>,
+    pub paths: Vec<WaypointPath>,
+    /// wind and currents, summed, carrying the boids
+    pub flow_fields: Vec<FlowField>,
+    /// behaviours of the boids, applied in order
+    pub rules: Vec<Box<dyn SteeringRule>>,
+    pub predators: Vec<Predator>,
+    pub predator_parameters: PredatorParameters,
+    catches: usize,
+    index: Box<dyn SpatialIndex>,
+    seed: u64,
+    rng: StdRng,
+}
+
+impl World {
+    pub fn new(nb_birds: usize, playfield: f32) -> Self {
+        World::with_index(nb_birds, playfield, IndexKind::Grid)
+    }
+
+    pub fn with_index(nb_birds: usize, playfield: f32, index_kind: IndexKind) -> Self {
+        World {
+            playfield_size: playfield,
+            step_mode: StepMode::Sequential,
+            parameters: Parameters::new(),
+            other_species: vec![],
+            interactions: InteractionMatrix::new(1),
+            personalities: PersonalityDistributions::new(),
+            current: vec![Boid::new(); nb_birds],
+            next: vec![Boid::new(); nb_birds],
+            obstacles: vec![],
+            attractors: vec![],
+            paths: vec![],
+            flow_fields: vec![],
+            rules: default_rules(),
+            predators: vec![],
+            predator_parameters: PredatorParameters::new(),
+            catches: 0,
+            index: index_kind.create(),
+            seed: 0,
+            rng: StdRng::seed_from_u64(0),
+        }
+        .with_seed(rand::random())
+    }
+
+    /// reseeds the random generator used by `initialize` and `compute`.
+    /// Two worlds with the same seed, step mode and inputs evolve identically.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.set_seed(seed);
+        self
+    }
+
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = seed;
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn nb_species(&self) -> usize {
+        1 + self.other_species.len()
+    }
+
+    /// adds a species and returns its id. Its interactions with the other species
+    /// are initialized with `Interaction::other()`
+    pub fn add_species(&mut self, parameters: Parameters) -> usize {
+        self.other_species.push(parameters);
+        self.interactions.resize(self.nb_species());
+        self.nb_species() - 1
+    }
+
+    pub fn species_parameters(&self, species: usize) -> &Parameters {
+        if species == 0 {
+            &self.parameters
+        } else {
+            &self.other_species[species - 1]
+        }
+    }
+
+    pub fn species_parameters_mut(&mut self, species: usize) -> &mut Parameters {
+        if species == 0 {
+            &mut self.parameters
+        } else {
+            &mut self.other_species[species - 1]
+        }
+    }
+
+    pub fn initialize(&mut self) {
+        let rng = &mut self.rng;
+        for boid in self.current.iter_mut() {
+            // let t: f32 = rng.gen::<f32>() * 2.0 * PI;
+            // let u = rng.gen::<f32>() + rng.gen::<f32>();
+            // let r: f32 = if u > 1.0 { 2.0 - u } else { u };
+
+            // boid.position.x = r * t.cos() * self.playfield_size;
+            // boid.position.y = r * t.sin() * self.playfield_size;
+            boid.position.x = (rng.gen::<f32>() - 0.5) * self.playfield_size;
+            boid.position.y = (rng.gen::<f32>() - 0.5) * self.playfield_size;
+            boid.velocity.x = (rng.gen::<f32>() - 0.5) * self.playfield_size * 0.1;
+            boid.velocity.y = (rng.gen::<f32>() - 0.5) * self.playfield_size * 0.1;
+            boid.update_speed();
+            boid.personality = self.personalities.sample(rng);
+
+            let parameters = if boid.species == 0 {
+                &self.parameters
+            } else {
+                &self.other_species[boid.species - 1]
+            };
+            let speed = boid.personality.speed;
+            boid.clamp_speed(parameters.min_bird_speed * speed, parameters.max_bird_speed * speed, rng);
+        }
+    }
+
+    /// adds a predator at a random position of the playfield
+    pub fn add_predator(&mut self) {
+        let rng = &mut self.rng;
+        let position = Vector {
+            x: (rng.gen::<f32>() - 0.5) * self.playfield_size,
+            y: (rng.gen::<f32>() - 0.5) * self.playfield_size,
+        };
+        let mut velocity = Vector::new();
+        velocity.set_random(self.predator_parameters.min_speed, rng);
+        self.predators.push(Predator::new(position, velocity));
+    }
+
+    /// velocity of the flow at `position`
+    pub fn flow_at(&self, position: &Vector) -> Vector {
+        let mut flow = Vector::new();
+        for field in self.flow_fields.iter() {
+            flow.add(&field.sample(position));
+        }
+        flow
+    }
+
+    /// number of boids caught by the predators since the creation of the world
+    pub fn catches(&self) -> usize {
+        self.catches
+    }
+
+    pub(crate) fn set_catches(&mut self, catches: usize) {
+        self.catches = catches;
+    }
+
+    pub fn compute(&mut self, dt: f32) {
+        self.rebuild_index();
+        let mut rngs = chunk_generators(self.step_mode, &mut self.rng);
+
+        let mut next = std::mem::take(&mut self.next);
+        match self.step_mode {
+            StepMode::Sequential => self.compute_range(0, &mut next, dt, &mut rngs[0], &mut |_, _, _| {}),
+            StepMode::Parallel { .. } => self.compute_parallel(&mut next, dt, &mut rngs),
+        }
+        self.next = next;
+
+        self.move_predators(dt);
+
+        self.current.swap_with_slice(&mut self.next);
+
+        self.check_catches();
+    }
+
+    /// Contributions of the rules to the steering of the boids `boids`, one vector per rule in
+    /// the order of `rules`, zero for a rule that does not apply. They are the ones of the next
+    /// `compute(dt)`, which is run on copies of its random generators, up to the last of `boids`
+    /// in each chunk, so the world is not changed
+    pub fn compute_steering(&mut self, boids: &[usize], dt: f32) -> Vec<Vec<Vector>> {
+        self.rebuild_index();
+        let mut rngs = chunk_generators(self.step_mode, &mut self.rng.clone());
+        let chunk_size = self.current.len().div_ceil(rngs.len()).max(1);
+
+        let mut contributions = vec![vec![]; self.current.len()];
+        for &index in boids.iter() {
+            contributions[index] = vec![Vector::new(); self.rules.len()];
+        }
+        let mut record = |index: usize, rule: usize, contribution: &Vector| {
+            if let Some(recorded) = contributions[index].get_mut(rule) {
+                *recorded = *contribution;
+            }
+        };
+
+        let mut next = self.current.clone();
+        for (chunk_index, rng) in rngs.iter_mut().enumerate() {
+            // the draws for a boid depend on the boids before it in its chunk
+            let first = chunk_index * chunk_size;
+            let last = boids.iter().copied().filter(|&i| i >= first && i < first + chunk_size).max();
+            if let Some(last) = last {
+                self.compute_range(first, &mut next[first..=last], dt, rng, &mut record);
+            }
+        }
+        boids.iter().map(|&index| contributions[index].clone()).collect()
+    }
+
+    /// Pairs `(i, j)`, with `i < j`, of boids of which at least one sees the other,
+    /// as the steering of the next step would
+    pub fn visible_pairs(&mut self) -> Vec<(usize, usize)> {
+        self.rebuild_index();
+        // only used for the separation of boids at the same position, not needed here
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let mut neighbourhood = Neighbourhood::new();
+        let mut pairs = vec![];
+        for (index, boid) in self.current.iter().enumerate() {
+            self.find_neighbours(index, self.species_parameters(boid.species), &mut neighbourhood, &mut rng);
+            pairs.extend(neighbourhood.neighbours.iter().map(|n| (index.min(n.index), index.max(n.index))));
+        }
+        pairs.sort_unstable();
+        pairs.dedup();
+        pairs
+    }
+
+    fn rebuild_index(&mut self) {
+        let max_visibility_radius = self
+            .other_species
+            .iter()
+            .fold(self.parameters.visibility_radius, |r, p| r.max(p.visibility_radius));
+        self.index.rebuild(&self.current, max_visibility_radius);
+    }
+
+    /// predators choose their target from the boids at the beginning of the step
+    fn move_predators(&mut self, dt: f32) {
+        let mut predators = std::mem::take(&mut self.predators);
+        let mut buffer = Vec::new();
+        for predator in predators.iter_mut() {
+            predator.choose_target(self, &mut buffer);
+            predator.pursue(self, dt);
+            self.parameters.boundary_mode.apply(&mut predator.body, self.playfield_size);
+        }
+        self.predators = predators;
+    }
+
+    /// a caught boid is counted and put back at a random position
+    fn check_catches(&mut self) {
+        let mut predators = std::mem::take(&mut self.predators);
+        for predator in predators.iter_mut() {
+            let target = match predator.target {
+                Some(target) => target,
+                None => continue,
+            };
+            if predator::distance(self, &predator.body.position, &self.current[target].position) > self.predator_parameters.catch_radius {
+                continue;
+            }
+
+            self.catches += 1;
+            predator.target = None;
+            let boid = &mut self.current[target];
+            boid.position.x = (self.rng.gen::<f32>() - 0.5) * self.playfield_size;
+            boid.position.y = (self.rng.gen::<f32>() - 0.5) * self.playfield_size;
+        }
+        self.predators = predators;
+    }
+
+    /// Splits the boids in as many contiguous chunks as random generators, each one computed by
+    /// its own thread with its own generator. The generators are all created,
+    /// in chunk order, before the threads are started so the result only depends on
+    /// the number of threads, not on their scheduling.
+    fn compute_parallel(&self, next: &mut [Boid], dt: f32, rngs: &mut [StdRng]) {
+        let chunk_size = next.len().div_ceil(rngs.len()).max(1);
+
+        std::thread::scope(|scope| {
+            for ((chunk_index, chunk), rng) in next.chunks_mut(chunk_size).enumerate().zip(rngs.iter_mut()) {
+                scope.spawn(move || self.compute_range(chunk_index * chunk_size, chunk, dt, rng, &mut |_, _, _| {}));
+            }
+        });
+    }
+
+    /// computes the next state of the boids `first..first+targets.len()`, giving `record`
+    /// the index of the boid, the index of the rule and the contribution of every applied rule
+    fn compute_range(
+        &self,
+        first: usize,
+        targets: &mut [Boid],
+        dt: f32,
+        rng: &mut impl Rng,
+        record: &mut impl FnMut(usize, usize, &Vector),
+    ) {
+        let mut contribution = Vector::new();
+        let mut neighbourhood = Neighbourhood::new();
+
+        for (offset, target) in targets.iter_mut().enumerate() {
+            let index = first + offset;
+            let boid = &self.current[index];
+            let parameters = self.species_parameters(boid.species);
+            let nb_visible = self.find_neighbours(index, parameters, &mut neighbourhood, rng);
+            *target = *boid;
+            target.update_position(dt);
+            if !self.flow_fields.is_empty() {
+                target.position.add_scaled(&self.flow_at(&boid.position), dt);
+            }
+
+            let context = SteeringContext {
+                world: self,
+                index,
+                reference: boid,
+                parameters,
+                neighbours: &neighbourhood.neighbours,
+                jitter: neighbourhood.jitter,
+            };
+            let mut desired = target.velocity;
+            for (rule_index, rule) in self.rules.iter().enumerate() {
+                if rule.steer(&context, &mut contribution) {
+                    desired.add(&contribution);
+                    record(index, rule_index, &contribution);
+                }
+            }
+            match parameters.physics {
+                PhysicsModel::Direct => {
+                    target.velocity = desired;
+                    target.acceleration.clear();
+                }
+                PhysicsModel::Reynolds if dt <= 0.0 => target.acceleration.clear(),
+                PhysicsModel::Reynolds => {
+                    // force reaching the desired velocity in one step
+                    let mut force = desired;
+                    force.subtract(&target.velocity);
+                    force.scale(1.0 / dt);
+                    let norm = force.hypot();
+                    if norm > parameters.max_force {
+                        force.scale(parameters.max_force / norm);
+                    }
+                    target.acceleration = force;
+                    target.acceleration.scale(1.0 / parameters.mass);
+                    target.velocity.add_scaled(&target.acceleration, dt);
+                }
+            }
+
+            if nb_visible > 0 {
+                target.velocity.x +=
+                    target.velocity.x * (2.0 * rng.gen::<f32>() - 1.0) * RANDOM_FACTOR;
+                target.velocity.y +=
+                    target.velocity.y * (2.0 * rng.gen::<f32>() - 1.0) * RANDOM_FACTOR;
+            }
+            if dt > 0.0 {
+                let max_angle = (parameters.max_turn_rate * dt).to_radians();
+                let turn_rate = target.limit_turn(&boid.velocity, max_angle).to_degrees() / dt;
+                let full_bank_rate = if parameters.max_turn_rate.is_finite() {
+                    parameters.max_turn_rate.max(1e-6)
+                } else {
+                    FULL_BANK_TURN_RATE
+                };
+                target.bank_angle = MAX_BANK_ANGLE * (turn_rate / full_bank_rate).clamp(-1.0, 1.0);
+            }
+            target.update_speed();
+            target.clamp_speed(
+                parameters.min_bird_speed * boid.personality.speed,
+                parameters.max_bird_speed * boid.personality.speed,
+                rng,
+            );
+            self.parameters.boundary_mode.apply(target, self.playfield_size);
+        }
+    }
+
+    /// Fills `neighbourhood.neighbours` with the boids visible from the boid `reference_index`, itself excluded,
+    /// and returns their number. `parameters` are the ones of the species of the boid
+    fn find_neighbours(&self, reference_index: usize, parameters: &Parameters, neighbourhood: &mut Neighbourhood, rng: &mut impl Rng) -> usize {
+        let reference = &self.current[reference_index];
+        let sight = Sight::new(reference, parameters);
+        match parameters.neighbour_mode {
+            NeighbourMode::Metric => self.find_candidates(&reference.position, sight.radius, neighbourhood),
+            NeighbourMode::Topological { k } => self.find_nearest_candidates(reference, &sight, k, neighbourhood),
+        }
+        neighbourhood.neighbours.clear();
+        neighbourhood.jitter.clear();
+        let mut separation = Vector::new();
+
+        for &index in neighbourhood.candidates.iter() {
+            let boid = &self.current[index];
+            let position = self.parameters.boundary_mode.closest_image(&reference.position, &boid.position, self.playfield_size);
+            let visibility = self.compute_separation(*reference, &sight, &position, &mut separation);
+            if (visibility & VISIBLE) == 0 {
+                continue;
+            }
+            let in_safe_space = (visibility & IN_SAFE_SPACE) != 0;
+            if in_safe_space {
+                let norm2 = separation.norm();
+                if norm2<DEFAULT_BIRD_SIZE*0.1 {
+                    separation.set_random(DEFAULT_BIRD_MIN_SPEED, rng)
+                }
+            }
+            if index == reference_index {
+                // drawn at its place in the index order, as the other random separations
+                neighbourhood.jitter = if in_safe_space { separation } else { Vector::new() };
+                continue;
+            }
+            neighbourhood.neighbours.push(Neighbour {
+                index,
+                position,
+                velocity: boid.velocity,
+                separation,
+                in_safe_space,
+                interaction: self.interactions.get(reference.species, boid.species),
+            });
+        }
+        neighbourhood.neighbours.len()
+    }
+
+    /// Fills `result` with the indices of the boids within `radius` of `position`, through the
+    /// boundary in `Wrap` mode. The index must have been built from the current boids
+    pub(crate) fn boids_within(&self, position: &Vector, radius: f32, result: &mut Vec<usize>) {
+        let mut neighbourhood = Neighbourhood::new();
+        self.find_candidates(position, radius, &mut neighbourhood);
+        result.clear();
+        result.extend_from_slice(&neighbourhood.candidates);
+    }
+
+    /// Fills `neighbourhood.candidates` with the indices, in increasing order, of the boids
+    /// within the visibility radius of `position`, taking the wrapping of the playfield into account
+    fn find_candidates(&self, position: &Vector, radius: f32, neighbourhood: &mut Neighbourhood) {
+        self.parameters
+            .boundary_mode
+            .query_positions(position, radius, self.playfield_size, &mut neighbourhood.images);
+
+        self.index.within_radius(position, radius, &mut neighbourhood.candidates);
+        for image in neighbourhood.images.iter().skip(1) {
+            self.index.within_radius(image, radius, &mut neighbourhood.image_candidates);
+            neighbourhood.candidates.extend_from_slice(&neighbourhood.image_candidates);
+        }
+        if neighbourhood.images.len() > 1 {
+            neighbourhood.candidates.sort_unstable();
+            neighbourhood.candidates.dedup();
+        }
+    }
+
+    /// Fills `neighbourhood.candidates` with the indices, in increasing order, of `reference`
+    /// and of the `k` boids the closest to it that are not in its dead angle
+    fn find_nearest_candidates(&self, reference: &Boid, sight: &Sight, k: usize, neighbourhood: &mut Neighbourhood) {
+        let position = reference.position;
+        let nb_boids = self.current.len();
+        let mut separation = Vector::new();
+        let mut in_sight = |index: usize| -> Option<f32> {
+            let other = self.parameters.boundary_mode.closest_image(&position, &self.current[index].position, self.playfield_size);
+            if self.compute_separation(*reference, sight, &other, &mut separation) == NOT_VISIBLE {
+                None
+            } else {
+                Some(separation.norm())
+            }
+        };
+
+        // the nearest boids, dead angle included, until enough of them are visible
+        let mut count = (k + 1).min(nb_boids);
+        loop {
+            self.index.nearest(&position, count, &mut neighbourhood.candidates);
+            let nb_in_sight = neighbourhood.candidates.iter().filter(|i| in_sight(**i).is_some()).count();
+            if nb_in_sight > k || count >= nb_boids {
+                break;
+            }
+            count = (2 * count).min(nb_boids);
+        }
+
+        // boids on the other side of the playfield may be closer through the wrapping
+        if count < nb_boids {
+            if let Some(farthest) = neighbourhood.candidates.last() {
+                let mut offset = position;
+                offset.subtract(&self.current[*farthest].position);
+                let radius = offset.hypot();
+                self.parameters
+                    .boundary_mode
+                    .query_positions(&position, radius, self.playfield_size, &mut neighbourhood.images);
+                for image in neighbourhood.images.iter().skip(1) {
+                    self.index.within_radius(image, radius, &mut neighbourhood.image_candidates);
+                    neighbourhood.candidates.extend_from_slice(&neighbourhood.image_candidates);
+                }
+                neighbourhood.candidates.sort_unstable();
+                neighbourhood.candidates.dedup();
+            }
+        }
+
+        neighbourhood.nearest.clear();
+        for &index in neighbourhood.candidates.iter() {
+            if let Some(distance2) = in_sight(index) {
+                neighbourhood.nearest.push((distance2, index));
+            }
+        }
+        neighbourhood
+            .nearest
+            .sort_unstable_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal).then(a.1.cmp(&b.1)));
+        // the reference boid is at distance 0, it is kept with its k nearest neighbours
+        neighbourhood.candidates.clear();
+        neighbourhood
+            .candidates
+            .extend(neighbourhood.nearest.iter().take(k + 1).map(|n| n.1));
+        neighbourhood.candidates.sort_unstable();
+    }
+
+    fn compute_separation(&self, reference: Boid, sight: &Sight, other: &Vector, separation: &mut Vector) -> u8 {
+        let visibility_radius = sight.radius;
+        // the topological mode sees the nearest boids whatever their distance
+        let metric = sight.metric;
+        *separation = reference.position;
+        separation.subtract(other);
+
+        if metric && (separation.x.abs() > visibility_radius || separation.y.abs() > visibility_radius) {
+            return NOT_VISIBLE;
+        }
+
+        let distance = separation.hypot();
+        if metric && distance > visibility_radius {
+            return NOT_VISIBLE;
+        }
+        let prod = (separation.x * reference.velocity.x + separation.y * reference.velocity.y)
+            / (distance * reference.speed());
+        if prod < sight.cos_max_angle {
+            return NOT_VISIBLE;
+        }
+
+        if distance < visibility_radius * sight.safe_space_ratio {
+            return IN_SAFE_SPACE | VISIBLE;
+        }
+
+        VISIBLE
+    }
+}
+
+/// One generator per chunk of boids, derived from `rng`, so the sequential mode gives
+/// the same result as the parallel mode with one thread
+fn chunk_generators(step_mode: StepMode, rng: &mut StdRng) -> Vec<StdRng> {
+    let nb_chunks = match step_mode {
+        StepMode::Sequential => 1,
+        StepMode::Parallel { nb_threads } => nb_threads.max(1),
+    };
+    (0..nb_chunks).map(|_| StdRng::seed_from_u64(rng.gen())).collect()
+}
+
+/// what a boid sees : the parameters of its species adjusted to its personality
+struct Sight {
+    radius: f32,
+    cos_max_angle: f32,
+    safe_space_ratio: f32,
+    metric: bool,
+}
+
+impl Sight {
+    fn new(reference: &Boid, parameters: &Parameters) -> Self {
+        Sight {
+            radius: parameters.visibility_radius * reference.personality.visibility,
+            cos_max_angle: reference.personality.cos_max_angle(parameters),
+            safe_space_ratio: parameters.safe_space_ratio,
+            metric: parameters.neighbour_mode == NeighbourMode::Metric,
+        }
+    }
+}
+
+/// buffers reused from one boid to the other during the neighbours search
+struct Neighbourhood {
+    candidates: Vec<usize>,
+    images: Vec<Vector>,
+    image_candidates: Vec<usize>,
+    /// squared distance and index of the boids in sight, in topological mode
+    nearest: Vec<(f32, usize)>,
+    neighbours: Vec<Neighbour>,
+    /// random separation of the reference boid from itself
+    jitter: Vector,
+}
+
+impl Neighbourhood {
+    fn new() -> Self {
+        Neighbourhood {
+            candidates: vec![],
+            images: vec![],
+            image_candidates: vec![],
+            nearest: vec![],
+            neighbours: vec![],
+            jitter: Vector::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_same(lhs: &Vector, rhs: &Vector) {
+        assert_eq!(lhs.x.to_bits(), rhs.x.to_bits());
+        assert_eq!(lhs.y.to_bits(), rhs.y.to_bits());
+    }
+
+    fn run(step_mode: StepMode, seed: u64, nb_steps: usize) -> World {
+        let mut world = World::new(500, 5.0).with_seed(seed);
+        world.step_mode = step_mode;
+        world.initialize();
+        for _ in 0..nb_steps {
+            world.compute(0.02);
+        }
+        world
+    }
+
+    fn assert_same_boids(lhs: &World, rhs: &World) {
+        for (l, r) in lhs.current.iter().zip(rhs.current.iter()) {
+            assert_same(&l.position, &r.position);
+            assert_same(&l.velocity, &r.velocity);
+        }
+    }
+
+    #[test]
+    fn neighbours_do_not_depend_on_the_index() {
+        let kinds = [IndexKind::Grid, IndexKind::QuadTree, IndexKind::KdTree];
+        for &(nb_birds, playfield) in [(200, 2.0), (500, 10.0), (1000, 30.0)].iter() {
+            let mut reference = World::with_index(nb_birds, playfield, IndexKind::BruteForce);
+            reference.initialize();
+            reference.compute(0.02);
+            reference.index.rebuild(&reference.current, reference.parameters.visibility_radius);
+
+            for kind in kinds.iter() {
+                let mut world = World::with_index(nb_birds, playfield, *kind);
+                world.current = reference.current.clone();
+                world.index.rebuild(&world.current, world.parameters.visibility_radius);
+
+                let mut expected = Neighbourhood::new();
+                let mut actual = Neighbourhood::new();
+                for i in 0..world.current.len() {
+                    let mut rng = StdRng::seed_from_u64(i as u64);
+                    let expected_result = reference.find_neighbours(i, &reference.parameters, &mut expected, &mut rng);
+                    let mut rng = StdRng::seed_from_u64(i as u64);
+                    let actual_result = world.find_neighbours(i, &world.parameters, &mut actual, &mut rng);
+
+                    assert_eq!(expected_result, actual_result);
+                    assert_same(&expected.jitter, &actual.jitter);
+                    for (e, a) in expected.neighbours.iter().zip(actual.neighbours.iter()) {
+                        assert_eq!(e.index, a.index);
+                        assert_same(&e.position, &a.position);
+                        assert_same(&e.separation, &a.separation);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn topological_neighbours_are_the_nearest_ones_in_sight() {
+        let mut world = World::new(300, 3.0).with_seed(5);
+        world.parameters.boundary_mode = BoundaryMode::Wrap;
+        world.parameters.neighbour_mode = NeighbourMode::Topological { k: 6 };
+        world.initialize();
+        // spread over the whole playfield so some neighbours are found through the wrapping
+        let mut rng = StdRng::seed_from_u64(5);
+        for boid in world.current.iter_mut() {
+            boid.position.x = (rng.gen::<f32>() * 2.0 - 1.0) * world.playfield_size;
+            boid.position.y = (rng.gen::<f32>() * 2.0 - 1.0) * world.playfield_size;
+        }
+        world.index.rebuild(&world.current, world.parameters.visibility_radius);
+
+        let mut neighbourhood = Neighbourhood::new();
+        let mut separation = Vector::new();
+        for (i, boid) in world.current.iter().enumerate() {
+            let mut rng = StdRng::seed_from_u64(i as u64);
+            assert_eq!(world.find_neighbours(i, &world.parameters, &mut neighbourhood, &mut rng), 6);
+
+            let mut expected: Vec<(f32, usize)> = (0..world.current.len())
+                .filter_map(|j| {
+                    let other = world.parameters.boundary_mode.closest_image(&boid.position, &world.current[j].position, world.playfield_size);
+                    match world.compute_separation(*boid, &Sight::new(boid, &world.parameters), &other, &mut separation) {
+                        NOT_VISIBLE => None,
+                        _ => Some((separation.norm(), j)),
+                    }
+                })
+                .collect();
+            expected.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap().then(a.1.cmp(&b.1)));
+            // the boid itself is the nearest one
+            let mut expected: Vec<usize> = expected.iter().take(7).map(|e| e.1).filter(|&j| j != i).collect();
+            expected.sort_unstable();
+
+            let actual: Vec<usize> = neighbourhood.neighbours.iter().map(|n| n.index).collect();
+            assert_eq!(expected, actual);
+        }
+    }
+
+    #[test]
+    fn same_seed_gives_same_trajectories() {
+        for step_mode in [StepMode::Sequential, StepMode::Parallel { nb_threads: 4 }].iter() {
+            let first = run(*step_mode, 42, 20);
+            let second = run(*step_mode, 42, 20);
+            assert_same_boids(&first, &second);
+        }
+    }
+
+    #[test]
+    fn sequential_mode_matches_parallel_mode_with_one_thread() {
+        let sequential = run(StepMode::Sequential, 7, 20);
+        let parallel = run(StepMode::Parallel { nb_threads: 1 }, 7, 20);
+        assert_same_boids(&sequential, &parallel);
+    }
+}
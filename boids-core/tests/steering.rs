@@ -0,0 +1,435 @@
+use boids_core::attractor::Attractor;
+use boids_core::boid::Boid;
+use boids_core::boundary::BoundaryMode;
+use boids_core::flow::FlowField;
+use boids_core::obstacle::Obstacle;
+use boids_core::path::WaypointPath;
+use boids_core::predator::Predator;
+use boids_core::species::Interaction;
+use boids_core::steering::{SteeringContext, SteeringRule};
+use boids_core::vector::Vector;
+use boids_core::world::{NeighbourMode, PhysicsModel, StepMode, World};
+use std::sync::{Arc, Mutex};
+
+fn boid(x: f32, y: f32, vx: f32, vy: f32) -> Boid {
+    let mut boid = Boid::new();
+    boid.position = Vector { x, y };
+    boid.velocity = Vector { x: vx, y: vy };
+    boid.update_speed();
+    boid
+}
+
+fn world_with(boids: &[Boid], separation: f32, alignment: f32, cohesion: f32) -> World {
+    let mut world = World::new(boids.len(), 10.0);
+    world.current.copy_from_slice(boids);
+    world.parameters.separation_factor = separation;
+    world.parameters.alignment_factor = alignment;
+    world.parameters.cohesion_factor = cohesion;
+    world
+}
+
+#[test]
+fn alignment_turns_toward_the_heading_of_the_neighbours() {
+    // the neighbour is on the side of the reference boid, visible but not in its safe space
+    let mut world = world_with(&[boid(0.0, 0.0, 10.0, 0.0), boid(0.0, 0.5, 0.0, 10.0)], 0.0, 0.1, 0.0);
+
+    world.compute(0.01);
+
+    assert!(world.current[0].velocity.y > 0.0);
+    assert!(world.current[1].velocity.x > 0.0);
+}
+
+#[test]
+fn cohesion_turns_toward_the_center_of_the_neighbours() {
+    let mut world = world_with(&[boid(0.0, 0.0, 10.0, 0.0), boid(0.0, 0.55, 10.0, 0.0)], 0.0, 0.0, 0.5);
+
+    world.compute(0.01);
+
+    assert!(world.current[0].velocity.y > 0.0);
+    assert!(world.current[1].velocity.y < 0.0);
+}
+
+#[test]
+fn topological_mode_sees_the_nearest_boids_outside_the_dead_angle() {
+    // the closest boid is straight ahead, in the dead angle, the second one is far on the side
+    let boids = [boid(0.0, 0.0, 10.0, 0.0), boid(0.0, 2.0, 0.0, 10.0), boid(1.0, 0.0, 0.0, -10.0)];
+
+    let mut world = world_with(&boids, 0.0, 0.1, 0.0);
+    world.compute(0.001);
+    assert_eq!(world.current[0].velocity.y, 0.0);
+
+    let mut world = world_with(&boids, 0.0, 0.1, 0.0);
+    world.parameters.neighbour_mode = NeighbourMode::Topological { k: 1 };
+    world.compute(0.001);
+    assert!(world.current[0].velocity.y > 0.0);
+}
+
+#[test]
+fn reynolds_physics_limits_the_steering_force() {
+    let boids = [boid(0.0, 0.0, 10.0, 0.0), boid(0.0, 0.5, 0.0, 10.0)];
+    let mut world = world_with(&boids, 0.0, 0.5, 0.0);
+    world.compute(0.01);
+    let direct = world.current[0].velocity;
+
+    let mut world = world_with(&boids, 0.0, 0.5, 0.0);
+    world.parameters.physics = PhysicsModel::Reynolds;
+    world.parameters.max_force = 100.0;
+    world.parameters.mass = 2.0;
+    world.compute(0.01);
+    let reynolds = world.current[0].velocity;
+
+    assert!(reynolds.y > 0.0 && reynolds.y < direct.y);
+    let acceleration = world.current[0].acceleration;
+    assert!((acceleration.hypot() - 50.0).abs() < 1e-3);
+
+    // no time to integrate the force
+    world.compute(0.0);
+    assert_eq!(world.current[0].velocity, reynolds);
+    assert_eq!(world.current[0].acceleration, Vector::new());
+}
+
+#[test]
+fn turn_rate_is_limited() {
+    let mut world = world_with(&[boid(0.0, 0.0, 10.0, 0.0)], 0.0, 0.0, 0.0);
+    world.attractors.push(Attractor::new(Vector { x: -0.5, y: 0.2 }, 100.0, 1.0));
+    world.parameters.max_turn_rate = 90.0;
+
+    world.compute(0.1);
+
+    let velocity = world.current[0].velocity;
+    let angle = velocity.y.atan2(velocity.x).to_degrees();
+    assert!(angle > 0.0 && angle <= 9.0 + 1e-3, "{}", angle);
+    assert!((world.current[0].bank_angle - 60.0).abs() < 1e-3);
+}
+
+#[test]
+fn boids_use_their_own_visibility() {
+    let mut far_sighted = boid(0.0, 0.0, 10.0, 0.0);
+    far_sighted.personality.visibility = 3.0;
+    let mut world = world_with(&[far_sighted, boid(0.0, 1.0, 0.0, 10.0)], 0.0, 0.1, 0.0);
+
+    world.compute(0.001);
+
+    assert!(world.current[0].velocity.y > 0.0);
+    assert_eq!(world.current[1].velocity.x, 0.0);
+}
+
+#[test]
+fn wind_carries_the_boids() {
+    let mut world = world_with(&[boid(0.0, 0.0, 10.0, 0.0)], 0.0, 0.0, 0.0);
+    world.flow_fields.push(FlowField::uniform(0.0, 5.0));
+
+    world.compute(0.1);
+
+    assert!((world.current[0].position.y - 0.5).abs() < 1e-6);
+    assert_eq!(world.current[0].velocity.y, 0.0);
+}
+
+#[test]
+fn isolated_boids_keep_their_velocity() {
+    let mut world = world_with(&[boid(-2.0, 0.0, 10.0, 0.0), boid(2.0, 0.0, 0.0, 10.0)], 0.06, 0.1, 0.04);
+
+    world.compute(0.01);
+
+    assert_eq!(world.current[0].velocity.x, 10.0);
+    assert_eq!(world.current[0].velocity.y, 0.0);
+    assert_eq!(world.current[1].velocity.x, 0.0);
+    assert_eq!(world.current[1].velocity.y, 10.0);
+    assert!((world.current[0].position.x + 1.9).abs() < 1e-5);
+}
+
+#[test]
+fn speed_is_kept_within_limits() {
+    let mut world = world_with(&[boid(0.0, 0.0, 100.0, 0.0), boid(3.0, 0.0, 0.5, 0.0)], 0.06, 0.1, 0.04);
+
+    world.compute(0.01);
+
+    let min = world.parameters.min_bird_speed;
+    let max = world.parameters.max_bird_speed;
+    for boid in world.current.iter() {
+        assert!(boid.speed() >= min - 1e-4 && boid.speed() <= max + 1e-4);
+    }
+}
+
+#[test]
+fn separation_pushes_away_from_close_neighbours() {
+    // same seed so that the random separation each boid gets from itself is the same in both worlds
+    let mut above = world_with(&[boid(0.0, 0.0, 10.0, 0.0), boid(0.0, 0.2, 10.0, 0.0)], 0.06, 0.0, 0.0).with_seed(3);
+    let mut below = world_with(&[boid(0.0, 0.0, 10.0, 0.0), boid(0.0, -0.2, 10.0, 0.0)], 0.06, 0.0, 0.0).with_seed(3);
+
+    above.compute(0.01);
+    below.compute(0.01);
+
+    assert!(above.current[0].velocity.y < below.current[0].velocity.y);
+}
+
+#[test]
+fn wrapped_boids_see_each_other_across_the_sides() {
+    let mut world = world_with(&[boid(9.9, 0.0, 0.0, 10.0), boid(-9.8, 0.0, 0.0, 10.0)], 0.0, 0.0, 0.5);
+    world.parameters.boundary_mode = BoundaryMode::Wrap;
+
+    world.compute(0.01);
+
+    // the closest image of the other boid is beyond the side
+    assert!(world.current[0].velocity.x > 0.0);
+    assert!(world.current[1].velocity.x < 0.0);
+}
+
+#[test]
+fn boids_turn_away_from_obstacles_ahead() {
+    let mut world = world_with(&[boid(0.0, 0.0, 10.0, 0.0)], 0.0, 0.0, 0.0);
+    world.obstacles.push(Obstacle::circle(1.5, 0.3, 0.5));
+
+    world.compute(0.01);
+
+    assert!(world.current[0].velocity.y < 0.0);
+    assert!(world.current[0].velocity.x < 10.0);
+}
+
+#[test]
+fn boids_flee_visible_predators() {
+    let mut world = world_with(&[boid(0.0, 0.0, 10.0, 0.0)], 0.0, 0.0, 0.0);
+    world.predators.push(Predator::new(Vector { x: 0.0, y: -0.5 }, Vector { x: 10.0, y: 0.0 }));
+
+    world.compute(0.01);
+
+    assert!(world.current[0].velocity.y > 0.0);
+}
+
+#[test]
+fn attractors_pull_and_repulsors_push() {
+    let mut world = world_with(&[boid(0.0, 0.0, 10.0, 0.0), boid(5.0, 5.0, 10.0, 0.0)], 0.0, 0.0, 0.0);
+    world.attractors.push(Attractor::new(Vector { x: 0.0, y: 1.0 }, 2.0, 2.0));
+    world.attractors.push(Attractor::new(Vector { x: 5.0, y: 6.0 }, -2.0, 2.0));
+
+    world.compute(0.01);
+
+    assert!(world.current[0].velocity.y > 0.0);
+    assert!(world.current[1].velocity.y < 0.0);
+}
+
+#[test]
+fn boids_steer_back_to_their_path() {
+    let mut world = world_with(&[boid(0.0, 1.0, 10.0, 0.0), boid(0.0, 3.0, 10.0, 0.0)], 0.0, 0.0, 0.0);
+    let waypoints = vec![Vector { x: -5.0, y: 0.0 }, Vector { x: 5.0, y: 0.0 }];
+    world.paths.push(WaypointPath::new(waypoints, false, 0.5, 1.0).unwrap());
+    world.parameters.path = Some(0);
+
+    world.compute(0.01);
+
+    assert!(world.current[0].velocity.y < 0.0);
+    assert!(world.current[1].velocity.y < 0.0);
+}
+
+#[test]
+fn predators_catch_close_boids() {
+    let mut world = world_with(&[boid(0.05, 0.0, 0.0, 10.0), boid(5.0, 5.0, 0.0, 10.0)], 0.0, 0.0, 0.0);
+    world.predators.push(Predator::new(Vector { x: 0.0, y: 0.0 }, Vector { x: 10.0, y: 0.0 }));
+
+    world.compute(0.01);
+
+    assert_eq!(world.predators[0].target, None);
+    assert_eq!(world.catches(), 1);
+}
+
+#[test]
+fn predators_hunt_through_the_wrapped_boundary() {
+    // the boid is just across the seam, a playfield away without the wrapping
+    let mut world = world_with(&[boid(-9.5, 0.0, 0.0, 10.0)], 0.0, 0.0, 0.0);
+    world.parameters.boundary_mode = BoundaryMode::Wrap;
+    world.predators.push(Predator::new(Vector { x: 9.0, y: 0.0 }, Vector { x: 0.0, y: 10.0 }));
+
+    world.compute(0.01);
+
+    assert_eq!(world.predators[0].target, Some(0));
+    assert!(world.predators[0].body.velocity.x > 0.0);
+
+    let mut world = world_with(&[boid(-9.95, 0.0, 0.0, 10.0)], 0.0, 0.0, 0.0);
+    world.parameters.boundary_mode = BoundaryMode::Wrap;
+    world.predators.push(Predator::new(Vector { x: 9.95, y: 0.0 }, Vector { x: 0.0, y: 10.0 }));
+
+    world.compute(0.01);
+
+    assert_eq!(world.catches(), 1);
+}
+
+#[test]
+fn species_only_align_with_the_species_they_interact_with() {
+    let mut other = boid(0.0, 0.4, 0.0, 10.0);
+    other.species = 1;
+    let mut world = world_with(&[boid(0.0, 0.0, 10.0, 0.0), other], 0.0, 0.1, 0.0);
+    let parameters = world.parameters.clone();
+    assert_eq!(world.add_species(parameters), 1);
+
+    world.compute(0.001);
+    assert_eq!(world.current[0].velocity.y, 0.0);
+    assert_eq!(world.current[1].velocity.x, 0.0);
+
+    world.interactions.set(0, 1, Interaction::same());
+    world.compute(0.001);
+    assert!(world.current[0].velocity.y > 0.0);
+    assert_eq!(world.current[1].velocity.x, 0.0);
+}
+
+struct Drift;
+
+impl SteeringRule for Drift {
+    fn name(&self) -> &'static str {
+        "Drift"
+    }
+
+    fn steer(&self, _context: &SteeringContext, contribution: &mut Vector) -> bool {
+        *contribution = Vector { x: 0.0, y: 1.0 };
+        true
+    }
+}
+
+#[test]
+fn custom_rules_are_applied() {
+    let mut world = world_with(&[boid(0.0, 0.0, 10.0, 0.0)], 0.0, 0.0, 0.0);
+    world.rules.push(Box::new(Drift));
+
+    world.compute(0.01);
+
+    assert_eq!(world.current[0].velocity.y, 1.0);
+}
+
+struct CountNeighbours;
+
+impl SteeringRule for CountNeighbours {
+    fn name(&self) -> &'static str {
+        "Count neighbours"
+    }
+
+    fn steer(&self, context: &SteeringContext, contribution: &mut Vector) -> bool {
+        assert!(context.neighbours.iter().all(|n| n.index != context.index));
+        *contribution = Vector { x: 0.0, y: context.neighbours.len() as f32 };
+        true
+    }
+}
+
+#[test]
+fn neighbours_exclude_the_reference_boid() {
+    let mut world = world_with(&[boid(0.0, 0.0, 10.0, 0.0), boid(0.0, 0.5, 10.0, 0.0), boid(5.0, 0.0, 10.0, 0.0)], 0.0, 0.0, 0.0);
+    world.rules = vec![Box::new(CountNeighbours)];
+
+    let steering = world.compute_steering(&[0, 2], 0.01);
+
+    assert_eq!(steering[0][0].y, 1.0);
+    assert_eq!(steering[1][0].y, 0.0);
+}
+
+// positions and velocities of the boids of `default_rules_reproduce_the_former_steering`,
+// computed before the steering rules. The boundary does not wrap, since the predators
+// now hunt through the wrapped boundary
+const GOLDEN_BOIDS: [(f32, f32, f32, f32); 30] = [
+    (0.7992589, -1.5540607, 1.2784865, 4.833784),
+    (1.3747928, -1.8672242, -2.42444, -11.017937),
+    (0.8784417, -1.9557921, 3.1009035, 3.9222946),
+    (1.3101025, -1.9631934, 1.341922, 5.572853),
+    (-0.5982798, -1.6158633, -3.6566985, 4.1071324),
+    (-1.1902416, 0.66259754, 3.7569752, 4.002073),
+    (1.3745679, 1.7514919, -4.043466, -6.095793),
+    (-0.08168891, -0.92940265, 4.4374013, 2.304228),
+    (1.946175, -1.4556705, -2.8299804, 6.5647016),
+    (-0.5453079, -0.9999179, 4.677541, 1.7665257),
+    (-0.4179853, -1.4530842, -2.455232, 4.355667),
+    (-0.27166423, -0.33036166, 5.9125323, 3.6622443),
+    (1.0560356, 1.8559371, -4.463952, 2.6250286),
+    (1.2437634, 1.9926922, -3.2634463, 4.568367),
+    (1.3069543, 1.9971845, -4.2345285, -2.658716),
+    (-0.35771695, 0.20998964, 5.418575, 2.6496658),
+    (0.3906811, -0.8267953, 2.5247664, 4.3157334),
+    (0.83618325, 1.6205319, -4.2143703, -4.010149),
+    (1.9171149, -1.4438978, 1.0695014, 10.676758),
+    (1.1597984, -1.8088001, 3.409654, 3.6570837),
+    (0.18353644, -1.8028138, 3.8294172, -3.2148972),
+    (0.499846, 1.9538041, -4.9499145, 0.705938),
+    (1.2384503, 1.945004, -4.01323, 3.0563943),
+    (1.6225774, -1.818858, -2.3129458, 14.272006),
+    (1.202061, -1.35695, -5.774376, 0.64118457),
+    (0.0044447295, -1.1765469, 4.999363, 0.079813026),
+    (0.7479712, 1.785902, -4.531325, -2.1135504),
+    (-0.30783102, -0.5328248, 5.464471, 2.3104146),
+    (0.9302131, 1.8497157, -4.260662, -2.8594048),
+    (1.7696817, -1.3758181, 0.069407985, 6.8989277),
+];
+
+#[test]
+fn default_rules_reproduce_the_former_steering() {
+    let mut world = World::new(30, 2.0).with_seed(13);
+    world.parameters.boundary_mode = BoundaryMode::Reflect;
+    world.initialize();
+    world.add_predator();
+    for _ in 0..100 {
+        world.compute(0.01);
+    }
+
+    assert_eq!(world.catches(), 7);
+    for (boid, &(x, y, vx, vy)) in world.current.iter().zip(GOLDEN_BOIDS.iter()) {
+        assert_eq!((boid.position.x, boid.position.y, boid.velocity.x, boid.velocity.y), (x, y, vx, vy));
+    }
+}
+
+#[test]
+fn steering_components_are_the_contributions_of_the_rules() {
+    let mut world = world_with(&[boid(0.0, 0.0, 10.0, 0.0), boid(0.0, 0.5, 0.0, 10.0)], 0.0, 0.1, 0.0);
+    world.rules.push(Box::new(Drift));
+
+    let steering = world.compute_steering(&[0], 0.01);
+
+    assert_eq!(steering.len(), 1);
+    assert_eq!(steering[0].len(), world.rules.len());
+    // alignment, then drift
+    assert!(steering[0][1].y > 0.0);
+    assert_eq!(steering[0][world.rules.len() - 1], Vector { x: 0.0, y: 1.0 });
+}
+
+// boid and contribution of each call of a rule
+type Contributions = Arc<Mutex<Vec<(usize, Vector)>>>;
+
+/// forwards to a rule, keeping its contributions
+struct Recorded {
+    rule: Box<dyn SteeringRule>,
+    contributions: Contributions,
+}
+
+impl SteeringRule for Recorded {
+    fn name(&self) -> &'static str {
+        self.rule.name()
+    }
+
+    fn steer(&self, context: &SteeringContext, contribution: &mut Vector) -> bool {
+        let applied = self.rule.steer(context, contribution);
+        let recorded = if applied { *contribution } else { Vector::new() };
+        self.contributions.lock().unwrap().push((context.index, recorded));
+        applied
+    }
+}
+
+#[test]
+fn steering_components_are_the_ones_of_the_next_step() {
+    // dense enough for the random separations of boids at the same position to matter
+    let mut world = World::new(300, 2.0).with_seed(11);
+    world.step_mode = StepMode::Parallel { nb_threads: 3 };
+    world.initialize();
+    let records: Vec<Contributions> = world.rules.iter().map(|_| Arc::new(Mutex::new(vec![]))).collect();
+    world.rules = std::mem::take(&mut world.rules)
+        .into_iter()
+        .zip(records.iter())
+        .map(|(rule, contributions)| Box::new(Recorded { rule, contributions: contributions.clone() }) as Box<dyn SteeringRule>)
+        .collect();
+    let ids = [0, 57, 150, 299];
+
+    let steering = world.compute_steering(&ids, 0.02);
+    records.iter().for_each(|r| r.lock().unwrap().clear());
+    world.compute(0.02);
+
+    for (components, id) in steering.iter().zip(ids.iter()) {
+        for (component, record) in components.iter().zip(records.iter()) {
+            let record = record.lock().unwrap();
+            let (_, contribution) = record.iter().find(|(index, _)| index == id).unwrap();
+            assert_eq!(component, contribution);
+        }
+    }
+}
@@ -1,4 +0,0 @@
-pub mod boid;
-pub mod steering;
-pub mod vector;
-pub mod world;
@@ -1,8 +1,23 @@
 use raylib::prelude::*;
 use raylib::consts::*;
 
-use crate::data::boid::Boid;
-use crate::data::world::World;
+use boids_core::attractor::Attractor;
+use boids_core::boid::Boid;
+use boids_core::boundary::BoundaryMode;
+use boids_core::cli::{usage, Options};
+use boids_core::cluster::ClusterTracker;
+use boids_core::export::CsvExporter;
+use boids_core::metrics::FlockMetrics;
+use boids_core::obstacle::Obstacle;
+use boids_core::path::WaypointPath;
+use boids_core::personality::PersonalityDistributions;
+use boids_core::recording::Recording;
+use boids_core::scenario::Scenario;
+use boids_core::snapshot;
+use boids_core::stepper::Stepper;
+use boids_core::trajectory::TrajectoryWriter;
+use boids_core::vector::Vector;
+use boids_core::world::{NeighbourMode, PhysicsModel, StepMode, World};
 use gui::gui::{Gui, GuiData};
 use gui::widget::Widget::{VBox, Label, Slider};
 use gui::vbox::VBoxPar;
@@ -24,12 +39,93 @@ use gui::event::Event::Drag;
 use gui::label::LabelPar;
 use raylib::ease::Tween;
 use std::fs::File;
-use std::path::Path;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+const DEFAULT_TICK_RATE: f32 = 60.;
+const DEFAULT_MAX_SUBSTEPS: usize = 5;
+const DEFAULT_NB_PREDATORS: usize = 3;
+const PREDATOR_SIZE_FACTOR: f32 = 2.5;
+const DEFAULT_NB_SPECIES: usize = 2;
+const DEFAULT_WINDOW_WIDTH: i32 = 640;
+const DEFAULT_WINDOW_HEIGHT: i32 = 480;
+const DEFAULT_RESOURCE_DIR: &str = "resources";
+const FONT_FILE: &str = "FredokaOne-Regular.ttf";
+const SNAPSHOT_PATH: &str = "boids.snapshot";
+const RECORDING_PATH: &str = "boids.recording";
+const TRAJECTORY_PATH: &str = "boids.trajectory";
+const CSV_PATH: &str = "boids.csv";
+// relative spread of the traits of the boids
+const DEFAULT_PERSONALITY_STD_DEV: f32 = 0.1;
+const FLOW_ARROWS_PER_SIDE: usize = 16;
+// length of an arrow per unit of flow speed
+const FLOW_ARROW_SCALE: f32 = 0.2;
+const MOUSE_ATTRACTOR_STRENGTH: f32 = 4.0;
+const MOUSE_ATTRACTOR_RADIUS: f32 = 3.0;
+const SPECIES_COLORS: [Color; 4] = [Color::BLACK, Color::DARKBLUE, Color::DARKGREEN, Color::PURPLE];
+const CLUSTER_COLORS: [Color; 8] = [
+    Color::DARKBLUE,
+    Color::ORANGE,
+    Color::DARKGREEN,
+    Color::MAROON,
+    Color::VIOLET,
+    Color::GOLD,
+    Color::SKYBLUE,
+    Color::BROWN,
+];
+// boids out of any flock when coloured by cluster
+const LONE_BIRD_COLOR: Color = Color::LIGHTGRAY;
+const MIN_CLUSTER_SIZE: usize = 5;
+
+/// Options of the window, given on the command line with the options of the world
+struct WindowOptions {
+    width: i32,
+    height: i32,
+    resource_dir: PathBuf,
+}
 
-mod data;
+impl WindowOptions {
+    fn new() -> Self {
+        WindowOptions {
+            width: DEFAULT_WINDOW_WIDTH,
+            height: DEFAULT_WINDOW_HEIGHT,
+            resource_dir: PathBuf::from(DEFAULT_RESOURCE_DIR),
+        }
+    }
 
-const DEFAULT_NB_BIRDS: usize = 2000;
-const DEFAULT_WORLD_SIZE: f32 = 10.;
+    /// sets the option `name` if it is an option of the window, see `Options::parse_with`
+    fn parse_option(&mut self, name: &str, value: &str) -> Result<bool, String> {
+        match name {
+            "window" => {
+                let (width, height) = parse_window_size(value).ok_or_else(|| format!("expected WIDTHxHEIGHT, got '{}'", value))?;
+                self.width = width;
+                self.height = height;
+            }
+            "resources" => self.resource_dir = PathBuf::from(value),
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    fn usage() -> String {
+        format!(
+            "\x20 --window <w>x<h>     size of the window ({}x{})\n\
+             \x20 --resources <dir>    directory of the fonts ({})\n",
+            DEFAULT_WINDOW_WIDTH, DEFAULT_WINDOW_HEIGHT, DEFAULT_RESOURCE_DIR
+        )
+    }
+}
+
+fn parse_window_size(value: &str) -> Option<(i32, i32)> {
+    let (width, height) = value.split_once('x')?;
+    let width: i32 = width.trim().parse().ok()?;
+    let height: i32 = height.trim().parse().ok()?;
+    if width > 0 && height > 0 {
+        Some((width, height))
+    } else {
+        None
+    }
+}
 
 pub struct ScreenSize {
     pub width: i32,
@@ -41,8 +137,10 @@ const SEPARATION_ID: &str = "separation_id";
 const DEAD_ANGLE_ID: &str = "dead_angle_id";
 const SAFE_SPACE_RATIO_ID: &str = "safe_space_ratio_id";
 const ALIGNMENT_ID: &str = "alignment_id";
+const NB_NEIGHBOURS_ID: &str = "nb_neighbours_id";
 
-fn draw_birds(d: &mut impl RaylibDraw, boids: &[Boid], bird_size: f32) {
+/// `color` gives the colour of a bird from its index and itself
+fn draw_birds(d: &mut impl RaylibDraw, boids: &[Boid], bird_size: f32, color: impl Fn(usize, &Boid) -> Color) {
     {
         let size_factor: f32 = 1.2;
 
@@ -50,27 +148,157 @@ fn draw_birds(d: &mut impl RaylibDraw, boids: &[Boid], bird_size: f32) {
         let mut left_wing = Vector2::zero();
         let mut right_wing = Vector2::zero();
 
-        for boid in boids {
+        for (index, boid) in boids.iter().enumerate() {
             let nvx = size_factor * bird_size * boid.velocity.x / boid.speed();
             let nvy = size_factor * bird_size * boid.velocity.y / boid.speed();
             head.x = nvx + boid.position.x;
             head.y = nvy + boid.position.y;
 
-            left_wing.x = nvy * 0.3 + boid.position.x;
-            left_wing.y = -nvx * 0.3 + boid.position.y;
+            // a banking bird is seen from the side : its wings look narrower
+            let wing_width = 0.3 * boid.bank_angle.to_radians().cos();
+            left_wing.x = nvy * wing_width + boid.position.x;
+            left_wing.y = -nvx * wing_width + boid.position.y;
+
+            right_wing.x = -nvy * wing_width + boid.position.x;
+            right_wing.y = nvx * wing_width + boid.position.y;
 
-            right_wing.x = -nvy * 0.3 + boid.position.x;
-            right_wing.y = nvx * 0.3 + boid.position.y;
+            d.draw_triangle(head, left_wing, right_wing, color(index, boid));
+        }
+    }
+}
 
-            d.draw_triangle(head, left_wing, right_wing, Color::BLACK);
+fn draw_boundary(d: &mut impl RaylibDraw, boundary_mode: &BoundaryMode, playfield_size: f32) {
+    let color = Color::LIGHTGRAY;
+    match boundary_mode {
+        BoundaryMode::Circle => d.draw_circle_lines(0, 0, playfield_size, color),
+        _ => {
+            let corners = [
+                Vector2 { x: -playfield_size, y: -playfield_size },
+                Vector2 { x: playfield_size, y: -playfield_size },
+                Vector2 { x: playfield_size, y: playfield_size },
+                Vector2 { x: -playfield_size, y: playfield_size },
+            ];
+            for i in 0..corners.len() {
+                d.draw_line_v(corners[i], corners[(i + 1) % corners.len()], color);
+            }
         }
     }
 }
 
+fn draw_obstacles(d: &mut impl RaylibDraw, obstacles: &[Obstacle]) {
+    let color = Color::DARKGRAY;
+    for obstacle in obstacles {
+        match obstacle {
+            Obstacle::Circle { center, radius } => {
+                d.draw_circle_v(Vector2 { x: center.x, y: center.y }, *radius, color)
+            }
+            Obstacle::Box { min, max } => d.draw_rectangle_v(
+                Vector2 { x: min.x, y: min.y },
+                Vector2 { x: max.x - min.x, y: max.y - min.y },
+                color,
+            ),
+            Obstacle::Polygon { vertices } => {
+                // vertices are counter-clockwise with y up, so clockwise on screen : reverse them for raylib
+                let first = Vector2 { x: vertices[0].x, y: vertices[0].y };
+                for i in 1..vertices.len() - 1 {
+                    let current = Vector2 { x: vertices[i].x, y: vertices[i].y };
+                    let next = Vector2 { x: vertices[i + 1].x, y: vertices[i + 1].y };
+                    d.draw_triangle(first, next, current, color);
+                }
+            }
+        }
+    }
+}
+
+fn draw_attractors(d: &mut impl RaylibDraw, attractors: &[Attractor]) {
+    for attractor in attractors {
+        let color = if attractor.strength >= 0.0 { Color::GREEN } else { Color::ORANGE };
+        let center = Vector2 { x: attractor.position.x, y: attractor.position.y };
+        d.draw_circle_v(center, 0.1, color);
+        d.draw_ring(center, attractor.radius - 0.03, attractor.radius, 0, 360, 64, color);
+    }
+}
+
+fn draw_paths(d: &mut impl RaylibDraw, paths: &[WaypointPath]) {
+    let color = Color::SKYBLUE;
+    for path in paths {
+        let nb_segments = if path.closed { path.waypoints.len() } else { path.waypoints.len() - 1 };
+        for i in 0..nb_segments {
+            let start = path.waypoints[i];
+            let end = path.waypoints[(i + 1) % path.waypoints.len()];
+            d.draw_line_v(Vector2 { x: start.x, y: start.y }, Vector2 { x: end.x, y: end.y }, color);
+        }
+    }
+}
+
+fn draw_flow(d: &mut impl RaylibDraw, world: &World) {
+    let color = Color::LIGHTGRAY;
+    let step = 2.0 * world.playfield_size / FLOW_ARROWS_PER_SIDE as f32;
+    for row in 0..FLOW_ARROWS_PER_SIDE {
+        for column in 0..FLOW_ARROWS_PER_SIDE {
+            let position = Vector {
+                x: -world.playfield_size + (column as f32 + 0.5) * step,
+                y: -world.playfield_size + (row as f32 + 0.5) * step,
+            };
+            let flow = world.flow_at(&position);
+            let start = Vector2 { x: position.x, y: position.y };
+            let end = Vector2 {
+                x: position.x + flow.x * FLOW_ARROW_SCALE,
+                y: position.y + flow.y * FLOW_ARROW_SCALE,
+            };
+            d.draw_line_v(start, end, color);
+            // head of the arrow, a quarter of its length
+            let head = Vector2 { x: (start.x - end.x) * 0.25, y: (start.y - end.y) * 0.25 };
+            d.draw_line_v(end, Vector2 { x: end.x + head.x - head.y * 0.5, y: end.y + head.y + head.x * 0.5 }, color);
+            d.draw_line_v(end, Vector2 { x: end.x + head.x + head.y * 0.5, y: end.y + head.y - head.x * 0.5 }, color);
+        }
+    }
+}
+
+fn catches_text(catches: usize) -> String {
+    format!("Catches : {}", catches)
+}
+
+fn metrics_texts(metrics: &FlockMetrics) -> [String; 3] {
+    [
+        format!("Polarization : {:.2}", metrics.polarization),
+        format!("Milling : {:.2}", metrics.milling),
+        format!("Nearest : {:.3}", metrics.mean_nearest_distance),
+    ]
+}
+
+fn neighbour_mode_text(neighbour_mode: &NeighbourMode) -> String {
+    match neighbour_mode {
+        NeighbourMode::Metric => "Neighbours : metric (N)".to_string(),
+        NeighbourMode::Topological { k } => format!("Neighbours : {} nearest (N)", k),
+    }
+}
+
+fn physics_text(physics: &PhysicsModel) -> String {
+    format!("Physics : {} (M)", physics.name())
+}
+
+fn boundary_text(boundary_mode: &BoundaryMode) -> String {
+    format!("Boundary : {} (B)", boundary_mode.name())
+}
+
 pub struct BoidsModel {
     pub gui_width: f32,
     pub screen_size: ScreenSize,
     pub world: World,
+    pub stepper: Stepper,
+    pub interpolated: Vec<Boid>,
+    pub recorder: Option<Recorder>,
+    pub exporter: Option<CsvExporter<BufWriter<File>>>,
+    /// number of steps computed since the start
+    pub nb_steps: usize,
+}
+
+/// Run being recorded, both as a replayable recording and as a trajectory
+pub struct Recorder {
+    pub recording: Recording,
+    pub trajectory: TrajectoryWriter<BufWriter<File>>,
+    pub time: f32,
 }
 
 impl BoidsModel {
@@ -82,6 +310,64 @@ impl BoidsModel {
                 height: 0,
             },
             world: World::new(nb_birds, world_size),
+            stepper: Stepper::new(DEFAULT_TICK_RATE, DEFAULT_MAX_SUBSTEPS),
+            interpolated: Vec::with_capacity(nb_birds),
+            recorder: None,
+            exporter: None,
+            nb_steps: 0,
+        }
+    }
+
+    /// Sets a parameter of a species from its value written as in a snapshot,
+    /// recording the change if a run is recorded
+    pub fn set_parameter(&mut self, species: usize, name: &str, value: &str) {
+        let result = match self.recorder.as_mut() {
+            Some(recorder) => recorder.recording.set_parameter(&mut self.world, species, name, value),
+            None => snapshot::set_parameter(self.world.species_parameters_mut(species), name, value),
+        };
+        if let Err(e) = result {
+            eprintln!("Cannot set {} : {}", name, e);
+        }
+    }
+
+    pub fn start_recording(&mut self) {
+        let recorder = Recording::start(&mut self.world).and_then(|recording| {
+            let mut trajectory = TrajectoryWriter::create(TRAJECTORY_PATH, &self.world.current)?;
+            trajectory.write_frame(0.0, &self.world.current)?;
+            Ok(Recorder { recording, trajectory, time: 0.0 })
+        });
+        match recorder {
+            Ok(recorder) => {
+                println!("Recording started");
+                self.recorder = Some(recorder);
+            }
+            Err(e) => eprintln!("{}", e),
+        }
+    }
+
+    pub fn toggle_export(&mut self) {
+        match self.exporter.take() {
+            Some(mut exporter) => match exporter.flush() {
+                Ok(()) => println!("Boids exported to '{}'", CSV_PATH),
+                Err(e) => eprintln!("{}", e),
+            },
+            None => match CsvExporter::create(CSV_PATH) {
+                Ok(mut exporter) => {
+                    exporter.steering = true;
+                    println!("Exporting the boids");
+                    self.exporter = Some(exporter);
+                }
+                Err(e) => eprintln!("{}", e),
+            },
+        }
+    }
+
+    pub fn stop_recording(&mut self) {
+        if let Some(mut recorder) = self.recorder.take() {
+            match recorder.recording.save(RECORDING_PATH).and_then(|_| recorder.trajectory.flush()) {
+                Ok(()) => println!("Recording saved to '{}' and '{}'", RECORDING_PATH, TRAJECTORY_PATH),
+                Err(e) => eprintln!("{}", e),
+            }
         }
     }
 }
@@ -100,13 +386,61 @@ impl BoidsModel {
 }
 
 fn main() {
-    let mut app_state = BoidsModel::new(DEFAULT_NB_BIRDS, DEFAULT_WORLD_SIZE);
+    let mut window = WindowOptions::new();
+    let options = match Options::parse_with(std::env::args().skip(1), |name, value| window.parse_option(name, value)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, usage("boids", &WindowOptions::usage()));
+            exit(1);
+        }
+    };
+    if options.help {
+        println!("{}", usage("boids", &WindowOptions::usage()));
+        return;
+    }
 
+    let mut app_state = BoidsModel::new(options.nb_birds, options.world_size);
+    if let Some(seed) = options.seed {
+        app_state.world.set_seed(seed);
+    }
+
+    for _ in 1..DEFAULT_NB_SPECIES {
+        let parameters = app_state.world.parameters.clone();
+        app_state.world.add_species(parameters);
+    }
+    if let Err(e) = options.apply_parameters(&mut app_state.world) {
+        eprintln!("{}", e);
+        exit(1);
+    }
+    for (i, boid) in app_state.world.current.iter_mut().enumerate() {
+        boid.species = i % DEFAULT_NB_SPECIES;
+    }
+    app_state.world.personalities = PersonalityDistributions::normal(DEFAULT_PERSONALITY_STD_DEV);
     app_state.world.initialize();
+    for _ in 0..DEFAULT_NB_PREDATORS {
+        app_state.world.add_predator();
+    }
+
+    if let Some(scenario_path) = &options.scenario {
+        match Scenario::load(scenario_path) {
+            Ok(scenario) => {
+                scenario.apply(&mut app_state.world);
+                // the second species follows the first path of the scenario
+                if !app_state.world.paths.is_empty() && app_state.world.nb_species() > 1 {
+                    app_state.world.species_parameters_mut(1).path = Some(0);
+                }
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                exit(1);
+            }
+        }
+    }
+    app_state.world.step_mode = StepMode::parallel();
 
 
     let (mut rl, thread) = raylib::init()
-        .size(640, 480)
+        .size(window.width, window.height)
         .msaa_4x()
         .resizable()
         .vsync()
@@ -119,10 +453,15 @@ fn main() {
     let mut gui = Gui::new();
 
 
-    let font_path = "resources/FredokaOne-Regular.ttf";
+    let font_path = window.resource_dir.join(FONT_FILE);
+    let font_path = font_path.to_string_lossy();
+    if !Path::new(font_path.as_ref()).is_file() {
+        eprintln!("Cannot find the font '{}', see --resources", font_path);
+        exit(1);
+    }
 
-    gui.load_font(&mut rl, &thread, "default", font_path, 48, 200);
-    gui.load_font(&mut rl, &thread, "small", font_path, 20, 200);
+    gui.load_font(&mut rl, &thread, "default", &font_path, 48, 200);
+    gui.load_font(&mut rl, &thread, "small", &font_path, 20, 200);
 
     let red = Color::RED;
 
@@ -144,7 +483,7 @@ fn main() {
     };
 
 
-    {
+    let alignment_slider = {
         let par = LabelPar::new();
         par.set_text(&gui, "Alignment")
             .set_text_style("default")
@@ -160,10 +499,10 @@ fn main() {
             .set_text_style("default")
             .enable_fill_width(&gui, Enabled { weight: 1 });
 
-        gui.add_child(container, Slider(par));
-    }
+        gui.add_child(container, Slider(par))
+    };
 
-    {
+    let cohesion_slider = {
         let par = LabelPar::new();
         par.set_text(&gui, "Cohesion")
             .set_text_style("default")
@@ -180,10 +519,10 @@ fn main() {
             .set_text_style("default")
             .enable_fill_width(&gui, Enabled { weight: 1 });
 
-        gui.add_child(container, Slider(par));
-    }
+        gui.add_child(container, Slider(par))
+    };
 
-    {
+    let separation_slider = {
         let par = LabelPar::new();
         par.set_text(&gui, "Separation")
             .set_text_style("default")
@@ -200,10 +539,10 @@ fn main() {
             .set_text_style("default")
             .enable_fill_width(&gui, Enabled { weight: 1 });
 
-        gui.add_child(container, Slider(par));
-    }
+        gui.add_child(container, Slider(par))
+    };
 
-    {
+    let dead_angle_slider = {
         let par = LabelPar::new();
         par.set_text(&gui, "Dead Angle")
             .set_text_style("default")
@@ -220,10 +559,10 @@ fn main() {
             .set_text_style("default")
             .enable_fill_width(&gui, Enabled { weight: 1 });
 
-        gui.add_child(container, Slider(par));
-    }
+        gui.add_child(container, Slider(par))
+    };
 
-    {
+    let safe_space_ratio_slider = {
         let par = LabelPar::new();
         par.set_text(&gui, "Safe Space Ratio")
             .set_text_style("default")
@@ -240,8 +579,80 @@ fn main() {
             .set_text_style("default")
             .enable_fill_width(&gui, Enabled { weight: 1 });
 
-        gui.add_child(container, Slider(par));
-    }
+        gui.add_child(container, Slider(par))
+    };
+
+
+    let mut topological_k = match NeighbourMode::topological() {
+        NeighbourMode::Topological { k } => k,
+        NeighbourMode::Metric => 0,
+    };
+    let (neighbour_mode_label, nb_neighbours_slider) = {
+        let par = LabelPar::new();
+        par.set_text(&gui, &neighbour_mode_text(&app_state.world.parameters.neighbour_mode))
+            .set_text_style("default")
+            .set_border_style("none")
+            .set_padding(&gui, Padding::new(40.0, 0.0, 0.0, 0.0))
+            .enable_fill_width(&gui, Enabled { weight: 1 });
+        let label = gui.add_child(container, Label(par));
+        let par = SliderPar::new();
+        par.set_value(&gui, topological_k as f32)
+            .set_value_min(&gui, 1.0)
+            .set_value_max(&gui, 20.0)
+            .set_text_style("default")
+            .set_action_id(NB_NEIGHBOURS_ID)
+            .set_text_style("default")
+            .enable_fill_width(&gui, Enabled { weight: 1 });
+
+        let slider = gui.add_child(container, Slider(par));
+        (label, slider)
+    };
+
+
+    let physics_label = {
+        let par = LabelPar::new();
+        par.set_text(&gui, &physics_text(&app_state.world.parameters.physics))
+            .set_text_style("default")
+            .set_border_style("none")
+            .set_padding(&gui, Padding::new(40.0, 0.0, 0.0, 0.0))
+            .enable_fill_width(&gui, Enabled { weight: 1 });
+        gui.add_child(container, Label(par))
+    };
+
+
+    let boundary_label = {
+        let par = LabelPar::new();
+        par.set_text(&gui, &boundary_text(&app_state.world.parameters.boundary_mode))
+            .set_text_style("default")
+            .set_border_style("none")
+            .set_padding(&gui, Padding::new(40.0, 0.0, 0.0, 0.0))
+            .enable_fill_width(&gui, Enabled { weight: 1 });
+        gui.add_child(container, Label(par))
+    };
+
+
+    let catches_label = {
+        let par = LabelPar::new();
+        par.set_text(&gui, &catches_text(app_state.world.catches()))
+            .set_text_style("default")
+            .set_border_style("none")
+            .set_padding(&gui, Padding::new(40.0, 0.0, 0.0, 0.0))
+            .enable_fill_width(&gui, Enabled { weight: 1 });
+        gui.add_child(container, Label(par))
+    };
+    let mut displayed_catches = app_state.world.catches();
+
+    let metrics_labels: Vec<_> = metrics_texts(&FlockMetrics::compute(&app_state.world))
+        .iter()
+        .map(|text| {
+            let par = LabelPar::new();
+            par.set_text(&gui, text)
+                .set_text_style("default")
+                .set_border_style("none")
+                .enable_fill_width(&gui, Enabled { weight: 1 });
+            gui.add_child(container, Label(par))
+        })
+        .collect();
 
 
     let mut camera = Camera2D {
@@ -256,11 +667,16 @@ fn main() {
     app_state.screen_size.height = rl.get_screen_height();
 
     let mut mouse_state = MouseState::new();
+    // same as mouse_state but in world coordinates
+    let mut world_mouse_state = MouseState::new();
     let mut screen_size: Size = Size::new(rl.get_screen_width() as f32, rl.get_screen_height() as f32);
     let mut should_quit = false;
 
 
     let mut gui_visible = true;
+    let mut flow_visible = false;
+    // the boids are coloured by flock instead of species while set
+    let mut clusters: Option<ClusterTracker> = None;
     let mut offset = Vector2::zero();
 
     let mut tween: Option<Tween> = None;
@@ -270,6 +686,7 @@ fn main() {
         let mut d = rl.begin_drawing(&thread);
         let dt = d.get_frame_time();
         mouse_state.update(&d);
+        world_mouse_state.update_2d(&d, camera);
 
 
         if let Some(t) = tween.as_mut() {
@@ -286,6 +703,147 @@ fn main() {
             gui_visible = !gui_visible
         }
 
+        if d.is_key_released(KeyboardKey::KEY_B) {
+            let boundary_mode = app_state.world.parameters.boundary_mode.cycle();
+            app_state.set_parameter(0, "boundary_mode", &snapshot::boundary_mode_value(&boundary_mode));
+            if let Some(Label(par)) = gui.get_widget(boundary_label) {
+                par.set_text(&gui, &boundary_text(&boundary_mode));
+            }
+        }
+
+        // holding control, the left button attracts the boids to the cursor and the right one repels them.
+        // The attractors are not recorded, so they are disabled while recording
+        app_state.world.attractors.clear();
+        if d.is_key_down(KeyboardKey::KEY_LEFT_CONTROL) && app_state.recorder.is_none() {
+            let strength = if world_mouse_state.left().is_down() {
+                Some(MOUSE_ATTRACTOR_STRENGTH)
+            } else if world_mouse_state.right().is_down() {
+                Some(-MOUSE_ATTRACTOR_STRENGTH)
+            } else {
+                None
+            };
+            if let Some(strength) = strength {
+                let position = world_mouse_state.mouse_position();
+                let position = Vector { x: position.x, y: position.y };
+                app_state.world.attractors.push(Attractor::new(position, strength, MOUSE_ATTRACTOR_RADIUS));
+            }
+        }
+
+        if d.is_key_released(KeyboardKey::KEY_N) {
+            let neighbour_mode = match app_state.world.parameters.neighbour_mode {
+                NeighbourMode::Metric => NeighbourMode::Topological { k: topological_k },
+                NeighbourMode::Topological { .. } => NeighbourMode::Metric,
+            };
+            for species in 0..app_state.world.nb_species() {
+                app_state.set_parameter(species, "neighbour_mode", &snapshot::neighbour_mode_value(&neighbour_mode));
+            }
+            if let Some(Label(par)) = gui.get_widget(neighbour_mode_label) {
+                par.set_text(&gui, &neighbour_mode_text(&neighbour_mode));
+            }
+        }
+
+        if d.is_key_released(KeyboardKey::KEY_M) {
+            let physics = match app_state.world.parameters.physics {
+                PhysicsModel::Direct => PhysicsModel::Reynolds,
+                PhysicsModel::Reynolds => PhysicsModel::Direct,
+            };
+            for species in 0..app_state.world.nb_species() {
+                app_state.set_parameter(species, "physics", snapshot::physics_value(&physics));
+            }
+            if let Some(Label(par)) = gui.get_widget(physics_label) {
+                par.set_text(&gui, &physics_text(&physics));
+            }
+        }
+
+        if d.is_key_released(KeyboardKey::KEY_F) {
+            flow_visible = !flow_visible;
+        }
+
+        if d.is_key_released(KeyboardKey::KEY_S) {
+            match snapshot::save(&app_state.world, SNAPSHOT_PATH) {
+                Ok(()) => println!("Snapshot saved to '{}'", SNAPSHOT_PATH),
+                Err(e) => eprintln!("{}", e),
+            }
+        }
+
+        if d.is_key_released(KeyboardKey::KEY_L) {
+            match snapshot::load(SNAPSHOT_PATH) {
+                Ok(world) => {
+                    // the recorded run does not go on after a load
+                    app_state.stop_recording();
+                    app_state.world = world;
+                    // neither the interpolation nor the clusters go on from the discarded world
+                    app_state.stepper.reset();
+                    if clusters.is_some() {
+                        let mut tracker = ClusterTracker::new(MIN_CLUSTER_SIZE);
+                        tracker.update(&mut app_state.world);
+                        clusters = Some(tracker);
+                    }
+                    let parameters = &app_state.world.parameters;
+                    if let Some(Label(par)) = gui.get_widget(boundary_label) {
+                        par.set_text(&gui, &boundary_text(&parameters.boundary_mode));
+                    }
+                    if let Some(Label(par)) = gui.get_widget(neighbour_mode_label) {
+                        par.set_text(&gui, &neighbour_mode_text(&parameters.neighbour_mode));
+                    }
+                    if let Some(Label(par)) = gui.get_widget(physics_label) {
+                        par.set_text(&gui, &physics_text(&parameters.physics));
+                    }
+                    if let NeighbourMode::Topological { k } = parameters.neighbour_mode {
+                        topological_k = k;
+                    }
+                    let sliders = [
+                        (alignment_slider, 100.0 * parameters.alignment_factor),
+                        (cohesion_slider, 100.0 * parameters.cohesion_factor),
+                        (separation_slider, 100.0 * parameters.separation_factor),
+                        (dead_angle_slider, parameters.dead_angle()),
+                        (safe_space_ratio_slider, 100.0 * parameters.safe_space_ratio),
+                        (nb_neighbours_slider, topological_k as f32),
+                    ];
+                    for (slider, value) in sliders.iter() {
+                        if let Some(Slider(par)) = gui.get_widget(*slider) {
+                            par.set_value(&gui, *value);
+                        }
+                    }
+                }
+                Err(e) => eprintln!("{}", e),
+            }
+        }
+
+        if d.is_key_released(KeyboardKey::KEY_R) {
+            if app_state.recorder.is_some() {
+                app_state.stop_recording();
+            } else {
+                app_state.start_recording();
+            }
+        }
+
+        if d.is_key_released(KeyboardKey::KEY_C) {
+            clusters = match clusters {
+                Some(_) => None,
+                None => {
+                    let mut tracker = ClusterTracker::new(MIN_CLUSTER_SIZE);
+                    tracker.update(&mut app_state.world);
+                    Some(tracker)
+                }
+            };
+        }
+
+        if d.is_key_released(KeyboardKey::KEY_E) {
+            app_state.toggle_export();
+        }
+
+        if d.is_key_released(KeyboardKey::KEY_P) {
+            let step_mode = match app_state.world.step_mode {
+                StepMode::Sequential => StepMode::parallel(),
+                StepMode::Parallel { .. } => StepMode::Sequential,
+            };
+            match app_state.recorder.as_mut() {
+                Some(recorder) => recorder.recording.set_step_mode(&mut app_state.world, step_mode),
+                None => app_state.world.step_mode = step_mode,
+            }
+        }
+
         if d.is_window_resized() {
             screen_size = Size::new(d.get_screen_width() as f32, d.get_screen_height() as f32);
             app_state.screen_size.width = d.get_screen_width();
@@ -301,29 +859,127 @@ fn main() {
 
         gui.layout_and_render(&mut d, &screen_size, &mouse_state, &offset);
 
+        app_state.stepper.interpolate(&app_state.world, &mut app_state.interpolated);
         {
             let mut d = d.begin_mode2D(camera);
+            draw_boundary(
+                &mut d,
+                &app_state.world.parameters.boundary_mode,
+                app_state.world.playfield_size,
+            );
+            if flow_visible {
+                draw_flow(&mut d, &app_state.world);
+            }
+            draw_obstacles(&mut d, &app_state.world.obstacles);
+            draw_paths(&mut d, &app_state.world.paths);
+            draw_attractors(&mut d, &app_state.world.attractors);
+            match clusters.as_ref() {
+                Some(clusters) => draw_birds(
+                    &mut d,
+                    &(app_state.interpolated[..]),
+                    app_state.world.parameters.bird_size,
+                    |index, _| match clusters.cluster_of(index) {
+                        Some(id) => CLUSTER_COLORS[id % CLUSTER_COLORS.len()],
+                        None => LONE_BIRD_COLOR,
+                    },
+                ),
+                None => draw_birds(
+                    &mut d,
+                    &(app_state.interpolated[..]),
+                    app_state.world.parameters.bird_size,
+                    |_, boid| SPECIES_COLORS[boid.species % SPECIES_COLORS.len()],
+                ),
+            }
+            let predators: Vec<Boid> = app_state.world.predators.iter().map(|p| p.body).collect();
             draw_birds(
                 &mut d,
-                &(app_state.world.current[..]),
-                app_state.world.parameters.bird_size,
+                &predators,
+                app_state.world.parameters.bird_size * PREDATOR_SIZE_FACTOR,
+                |_, _| Color::RED,
             );
         }
 
         let events = gui.get_events();
         for event in events.iter() {
             if let Drag(p) = event {
-                match p.action_id() {
-                    COHESION_ID => { app_state.world.parameters.cohesion_factor = p.value() / 100. }
-                    ALIGNMENT_ID => { app_state.world.parameters.alignment_factor = p.value() / 100. }
-                    SEPARATION_ID => { app_state.world.parameters.separation_factor = p.value() / 100. }
-                    DEAD_ANGLE_ID => { app_state.world.parameters.set_dead_angle(p.value()) }
-                    SAFE_SPACE_RATIO_ID => { app_state.world.parameters.safe_space_ratio = p.value() * 0.01 }
-                    &_ => {}
+                if p.action_id() == NB_NEIGHBOURS_ID {
+                    topological_k = p.value().round().max(1.0) as usize;
+                    if let NeighbourMode::Topological { .. } = app_state.world.parameters.neighbour_mode {
+                        let neighbour_mode = NeighbourMode::Topological { k: topological_k };
+                        let value = snapshot::neighbour_mode_value(&neighbour_mode);
+                        for species in 0..app_state.world.nb_species() {
+                            app_state.set_parameter(species, "neighbour_mode", &value);
+                        }
+                        if let Some(Label(par)) = gui.get_widget(neighbour_mode_label) {
+                            par.set_text(&gui, &neighbour_mode_text(&neighbour_mode));
+                        }
+                    }
+                }
+                let parameter = match p.action_id() {
+                    COHESION_ID => Some(("cohesion_factor", p.value() / 100.)),
+                    ALIGNMENT_ID => Some(("alignment_factor", p.value() / 100.)),
+                    SEPARATION_ID => Some(("separation_factor", p.value() / 100.)),
+                    DEAD_ANGLE_ID => Some(("dead_angle", p.value())),
+                    SAFE_SPACE_RATIO_ID => Some(("safe_space_ratio", p.value() * 0.01)),
+                    &_ => None,
+                };
+                if let Some((name, value)) = parameter {
+                    for species in 0..app_state.world.nb_species() {
+                        app_state.set_parameter(species, name, &value.to_string());
+                    }
                 }
             }
         }
 
-        app_state.world.compute(dt);
+        let nb_steps = app_state.stepper.advance(&mut app_state.world, dt);
+        app_state.nb_steps += nb_steps;
+        if let Some(exporter) = app_state.exporter.as_mut() {
+            if nb_steps > 0 {
+                if let Err(e) = exporter.export(&mut app_state.world, app_state.nb_steps, app_state.stepper.step()) {
+                    eprintln!("{}", e);
+                }
+            }
+        }
+        if let Some(recorder) = app_state.recorder.as_mut() {
+            let step = app_state.stepper.step();
+            for _ in 0..nb_steps {
+                recorder.recording.record_step(step);
+            }
+            // one frame per rendered frame, the intermediate steps are not kept
+            recorder.time += nb_steps as f32 * step;
+            if nb_steps > 0 {
+                if let Err(e) = recorder.trajectory.write_frame(recorder.time, &app_state.world.current) {
+                    eprintln!("{}", e);
+                }
+            }
+        }
+
+        if let Some(tracker) = clusters.as_mut() {
+            if nb_steps > 0 {
+                for event in tracker.update(&mut app_state.world) {
+                    println!("{}", event);
+                }
+            }
+        }
+
+        if nb_steps > 0 {
+            let texts = metrics_texts(&FlockMetrics::compute(&app_state.world));
+            for (label, text) in metrics_labels.iter().zip(texts.iter()) {
+                if let Some(Label(par)) = gui.get_widget(*label) {
+                    par.set_text(&gui, text);
+                }
+            }
+        }
+
+        if displayed_catches != app_state.world.catches() {
+            displayed_catches = app_state.world.catches();
+            if let Some(Label(par)) = gui.get_widget(catches_label) {
+                par.set_text(&gui, &catches_text(displayed_catches));
+            }
+        }
+    }
+    app_state.stop_recording();
+    if app_state.exporter.is_some() {
+        app_state.toggle_export();
     }
 }